@@ -0,0 +1,155 @@
+use crate::config::{Config, ConfigError};
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const SNAKE_SHEET: &str = "snake.png";
+pub const FONT_SHEET: &str = "font.png";
+pub const FONT_LAYOUT: &str = "font.cfg";
+pub const SETTINGS: &str = "settings.cfg";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io(String, io::Error),
+    Image(String, image::ImageError),
+    Config(String, ConfigError),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::Io(name, error) => write!(f, "{}: {}", name, error),
+            AssetError::Image(name, error) => write!(f, "{}: {}", name, error),
+            AssetError::Config(name, error) => write!(f, "{}: {}", name, error),
+        }
+    }
+}
+
+/// Where the game reads its sprite sheets and settings from.
+pub enum AssetSource {
+    /// Assets compiled into the binary.
+    Embedded,
+    /// Assets read from a directory on every load, used in development mode.
+    Directory(PathBuf),
+}
+
+impl AssetSource {
+    /// Picks the source from command line arguments.
+    ///
+    /// `--dev` reads the assets from the source tree, `--dev <dir>` from the given directory.
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+        while let Some(arg) = args.next() {
+            if arg == "--dev" {
+                let directory = args
+                    .next()
+                    .filter(|next| !next.starts_with("--"))
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("src/resources"));
+
+                return AssetSource::Directory(directory);
+            }
+        }
+
+        AssetSource::Embedded
+    }
+
+    pub fn load(&self, name: &str) -> Result<Cow<'static, [u8]>, AssetError> {
+        self.load_raw(name)
+            .map_err(|error| AssetError::Io(name.to_string(), error))
+    }
+
+    pub fn load_image(&self, name: &str) -> Result<image::DynamicImage, AssetError> {
+        let bytes = self.load(name)?;
+        image::load_from_memory_with_format(&bytes, image::ImageFormat::PNG)
+            .map_err(|error| AssetError::Image(name.to_string(), error))
+    }
+
+    pub fn load_config(&self, name: &str) -> Result<Config, AssetError> {
+        let text = self.load_string(name)?;
+        Config::parse(&text).map_err(|error| AssetError::Config(name.to_string(), error))
+    }
+
+    pub fn load_string(&self, name: &str) -> Result<String, AssetError> {
+        let bytes = self.load(name)?;
+        String::from_utf8(bytes.into_owned()).map_err(|error| {
+            AssetError::Io(
+                name.to_string(),
+                io::Error::new(io::ErrorKind::InvalidData, error),
+            )
+        })
+    }
+
+    fn load_raw(&self, name: &str) -> io::Result<Cow<'static, [u8]>> {
+        match self {
+            AssetSource::Embedded => match name {
+                SNAKE_SHEET => Ok(Cow::Borrowed(include_bytes!("resources/snake.png"))),
+                FONT_SHEET => Ok(Cow::Borrowed(include_bytes!("resources/font.png"))),
+                FONT_LAYOUT => Ok(Cow::Borrowed(include_bytes!("resources/font.cfg"))),
+                SETTINGS => Ok(Cow::Borrowed(include_bytes!("resources/settings.cfg"))),
+                _ => Err(io::Error::new(io::ErrorKind::NotFound, name.to_string())),
+            },
+            AssetSource::Directory(directory) => fs::read(directory.join(name)).map(Cow::Owned),
+        }
+    }
+
+    /// Creates a watcher for the given assets, if the source can change at all.
+    pub fn watch(&self, names: &[&str]) -> Option<AssetWatcher> {
+        match self {
+            AssetSource::Embedded => None,
+            AssetSource::Directory(directory) => Some(AssetWatcher::new(
+                names.iter().map(|name| directory.join(name)).collect(),
+            )),
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Polls modification times of asset files.
+pub struct AssetWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    last_poll: Instant,
+}
+
+impl AssetWatcher {
+    fn new(paths: Vec<PathBuf>) -> Self {
+        AssetWatcher {
+            files: paths
+                .into_iter()
+                .map(|path| {
+                    let modified = modified_time(&path);
+                    (path, modified)
+                })
+                .collect(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Returns the file names which changed since the previous poll.
+    pub fn poll(&mut self) -> Vec<String> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+        for (path, last_modified) in &mut self.files {
+            let modified = modified_time(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                if let Some(name) = path.file_name() {
+                    changed.push(name.to_string_lossy().into_owned());
+                }
+            }
+        }
+
+        changed
+    }
+}
@@ -0,0 +1,278 @@
+use crate::config::{Config, ConfigError};
+
+#[cfg(feature = "sound")]
+mod synth;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Sound {
+    Eat,
+    Turn,
+    Death,
+    LevelUp,
+    PowerUp,
+    Poison,
+    Victory,
+    MenuMove,
+    MenuSelect,
+}
+
+/// Plays the sounds, so that the game doesn't have to know about the audio device.
+pub trait AudioBackend {
+    /// Plays the sound once, `volume` goes from 0 to 1.
+    fn play(&mut self, sound: Sound, volume: f32);
+
+    /// Starts looping the background music, restarting it when it already plays.
+    fn start_music(&mut self, volume: f32);
+
+    fn stop_music(&mut self);
+
+    fn set_music_volume(&mut self, volume: f32);
+}
+
+/// Backend used when there is no audio device, or the game is built without sound.
+pub struct NullAudio;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AudioSettings {
+    pub sound_volume: f32,
+    pub music_volume: f32,
+    pub muted: bool,
+}
+
+/// Applies the volume settings and mute on top of the backend.
+pub struct Audio {
+    backend: Box<dyn AudioBackend>,
+    settings: AudioSettings,
+    music_playing: bool,
+}
+
+impl AudioBackend for NullAudio {
+    fn play(&mut self, _sound: Sound, _volume: f32) {}
+
+    fn start_music(&mut self, _volume: f32) {}
+
+    fn stop_music(&mut self) {}
+
+    fn set_music_volume(&mut self, _volume: f32) {}
+}
+
+/// Uses the default audio device when the game is built with the `sound` feature.
+pub fn default_backend() -> Box<dyn AudioBackend> {
+    #[cfg(feature = "sound")]
+    {
+        match synth::SynthAudio::new() {
+            Some(backend) => return Box::new(backend),
+            None => eprintln!("No audio device found, playing without sound."),
+        }
+    }
+
+    Box::new(NullAudio)
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            sound_volume: 0.8,
+            music_volume: 0.5,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// Reads `sound_volume` and `music_volume` (from 0 to 1) and `mute` keys.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let defaults = AudioSettings::default();
+        let sound_volume: f32 = config.get("sound_volume")?.unwrap_or(defaults.sound_volume);
+        let music_volume: f32 = config.get("music_volume")?.unwrap_or(defaults.music_volume);
+
+        Ok(AudioSettings {
+            sound_volume: sound_volume.clamp(0.0, 1.0),
+            music_volume: music_volume.clamp(0.0, 1.0),
+            muted: config.get("mute")?.unwrap_or(defaults.muted),
+        })
+    }
+}
+
+impl Audio {
+    pub fn new(backend: Box<dyn AudioBackend>, settings: AudioSettings) -> Self {
+        Audio {
+            backend,
+            settings,
+            music_playing: false,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: AudioSettings) {
+        self.settings = settings;
+        self.update_music_volume();
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        if !self.settings.muted && self.settings.sound_volume > 0.0 {
+            self.backend.play(sound, self.settings.sound_volume);
+        }
+    }
+
+    pub fn start_music(&mut self) {
+        self.music_playing = true;
+        self.backend.start_music(self.music_volume());
+    }
+
+    pub fn stop_music(&mut self) {
+        self.music_playing = false;
+        self.backend.stop_music();
+    }
+
+    /// Silences everything, the music keeps going quietly so that unmuting doesn't restart it.
+    pub fn toggle_mute(&mut self) {
+        self.settings.muted = !self.settings.muted;
+        self.update_music_volume();
+    }
+
+    fn music_volume(&self) -> f32 {
+        match self.settings.muted {
+            true => 0.0,
+            false => self.settings.music_volume,
+        }
+    }
+
+    fn update_music_volume(&mut self) {
+        if self.music_playing {
+            self.backend.set_music_volume(self.music_volume());
+        }
+    }
+}
+
+/// Remembers what was played instead of playing it.
+#[cfg(test)]
+pub mod recording {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone, PartialEq, Debug)]
+    pub enum Played {
+        Sound(Sound, f32),
+        MusicStarted(f32),
+        MusicStopped,
+        MusicVolume(f32),
+    }
+
+    /// Clones share the log, so one can be given to `Audio` and the other one inspected.
+    #[derive(Clone, Default)]
+    pub struct RecordingAudio {
+        log: Rc<RefCell<Vec<Played>>>,
+    }
+
+    impl RecordingAudio {
+        pub fn played(&self) -> Vec<Played> {
+            self.log.borrow().clone()
+        }
+
+        pub fn sounds(&self) -> Vec<Sound> {
+            self.log
+                .borrow()
+                .iter()
+                .filter_map(|played| match played {
+                    Played::Sound(sound, _) => Some(*sound),
+                    _ => None,
+                })
+                .collect()
+        }
+    }
+
+    impl AudioBackend for RecordingAudio {
+        fn play(&mut self, sound: Sound, volume: f32) {
+            self.log.borrow_mut().push(Played::Sound(sound, volume));
+        }
+
+        fn start_music(&mut self, volume: f32) {
+            self.log.borrow_mut().push(Played::MusicStarted(volume));
+        }
+
+        fn stop_music(&mut self) {
+            self.log.borrow_mut().push(Played::MusicStopped);
+        }
+
+        fn set_music_volume(&mut self, volume: f32) {
+            self.log.borrow_mut().push(Played::MusicVolume(volume));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::recording::{Played, RecordingAudio};
+    use super::*;
+
+    fn recorded_audio(settings: AudioSettings) -> (Audio, RecordingAudio) {
+        let recording = RecordingAudio::default();
+        (Audio::new(Box::new(recording.clone()), settings), recording)
+    }
+
+    #[test]
+    fn test_settings_from_config() {
+        let config = Config::parse("sound_volume = 0.25\nmusic_volume = 3\nmute = true").unwrap();
+        assert_eq!(
+            AudioSettings::from_config(&config).unwrap(),
+            AudioSettings {
+                sound_volume: 0.25,
+                music_volume: 1.0,
+                muted: true,
+            }
+        );
+
+        let config = Config::parse("sound_volume = loud").unwrap();
+        assert!(AudioSettings::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_play_with_volume() {
+        let (mut audio, recording) = recorded_audio(AudioSettings::default());
+        audio.play(Sound::Eat);
+        audio.start_music();
+        audio.stop_music();
+
+        assert_eq!(
+            recording.played(),
+            vec![
+                Played::Sound(Sound::Eat, 0.8),
+                Played::MusicStarted(0.5),
+                Played::MusicStopped,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mute() {
+        let (mut audio, recording) = recorded_audio(AudioSettings::default());
+        audio.start_music();
+        audio.toggle_mute();
+        audio.play(Sound::Death);
+        audio.toggle_mute();
+        audio.play(Sound::Turn);
+
+        assert_eq!(
+            recording.played(),
+            vec![
+                Played::MusicStarted(0.5),
+                Played::MusicVolume(0.0),
+                Played::MusicVolume(0.5),
+                Played::Sound(Sound::Turn, 0.8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_silent_sounds_are_skipped() {
+        let settings = AudioSettings {
+            sound_volume: 0.0,
+            ..AudioSettings::default()
+        };
+        let (mut audio, recording) = recorded_audio(settings);
+        audio.play(Sound::LevelUp);
+
+        assert!(recording.sounds().is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+use super::{AudioBackend, Sound};
+use rodio::source::{from_iter, SineWave};
+use rodio::{Device, Sink, Source};
+use std::time::Duration;
+
+// (frequency in Hz, length in milliseconds), zero frequency is a rest
+type Note = (u32, u64);
+
+const EAT: &[Note] = &[(660, 40), (990, 60)];
+const TURN: &[Note] = &[(220, 25)];
+const DEATH: &[Note] = &[(330, 120), (262, 120), (196, 120), (131, 300)];
+const LEVEL_UP: &[Note] = &[(523, 80), (659, 80), (784, 80), (1047, 160)];
+const POWER_UP: &[Note] = &[(392, 50), (523, 50), (392, 50), (784, 120)];
+const POISON: &[Note] = &[(330, 60), (311, 60), (294, 60), (277, 120)];
+const VICTORY: &[Note] = &[
+    (523, 100),
+    (659, 100),
+    (784, 100),
+    (1047, 200),
+    (0, 60),
+    (784, 100),
+    (1047, 400),
+];
+const MENU_MOVE: &[Note] = &[(523, 30)];
+const MENU_SELECT: &[Note] = &[(784, 50), (1047, 90)];
+
+const MUSIC: &[Note] = &[
+    (262, 200),
+    (0, 100),
+    (330, 200),
+    (0, 100),
+    (392, 200),
+    (330, 200),
+    (294, 300),
+    (0, 300),
+    (247, 200),
+    (0, 100),
+    (294, 200),
+    (0, 100),
+    (392, 200),
+    (294, 200),
+    (262, 300),
+    (0, 300),
+];
+
+// sine waves are loud, keep everything well below clipping
+const MASTER_VOLUME: f32 = 0.2;
+
+/// Plays short synthesized tunes, so that the game doesn't need any sound files.
+pub struct SynthAudio {
+    device: Device,
+    music: Option<Sink>,
+}
+
+fn tune(notes: &'static [Note]) -> impl Source<Item = f32> + Send {
+    from_iter(notes.iter().map(|&(frequency, milliseconds)| {
+        SineWave::new(frequency).take_duration(Duration::from_millis(milliseconds))
+    }))
+}
+
+fn notes(sound: Sound) -> &'static [Note] {
+    match sound {
+        Sound::Eat => EAT,
+        Sound::Turn => TURN,
+        Sound::Death => DEATH,
+        Sound::LevelUp => LEVEL_UP,
+        Sound::PowerUp => POWER_UP,
+        Sound::Poison => POISON,
+        Sound::Victory => VICTORY,
+        Sound::MenuMove => MENU_MOVE,
+        Sound::MenuSelect => MENU_SELECT,
+    }
+}
+
+impl SynthAudio {
+    pub fn new() -> Option<Self> {
+        rodio::default_output_device().map(|device| SynthAudio {
+            device,
+            music: None,
+        })
+    }
+}
+
+impl AudioBackend for SynthAudio {
+    fn play(&mut self, sound: Sound, volume: f32) {
+        rodio::play_raw(
+            &self.device,
+            tune(notes(sound)).amplify(volume * MASTER_VOLUME),
+        );
+    }
+
+    fn start_music(&mut self, volume: f32) {
+        self.stop_music();
+
+        let sink = Sink::new(&self.device);
+        sink.set_volume(volume * MASTER_VOLUME);
+        sink.append(tune(MUSIC).repeat_infinite());
+        self.music = Some(sink);
+    }
+
+    fn stop_music(&mut self) {
+        if let Some(sink) = self.music.take() {
+            sink.stop();
+        }
+    }
+
+    fn set_music_volume(&mut self, volume: f32) {
+        if let Some(sink) = &self.music {
+            sink.set_volume(volume * MASTER_VOLUME);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::{Duration, Instant};
+
+// a zero step would never let the accumulator drain
+const MIN_STEP: Duration = Duration::from_micros(1);
+
+/// Source of time for the simulation, so that tests don't have to wait for real time to pass.
+pub trait Clock {
+    /// Time passed since the clock was created.
+    fn now(&self) -> Duration;
+}
+
+pub struct RealClock {
+    start: Instant,
+}
+
+/// Runs the simulation in steps of equal length, however often it is updated.
+///
+/// Time which is not enough for a whole step is kept for the next update, so no time is lost to
+/// rounding. After a stall at most `max_steps` are made at once and the rest of the time is
+/// dropped, so that the game doesn't rush forward.
+pub struct FixedTimestep {
+    step: Duration,
+    max_steps: u32,
+    accumulator: Duration,
+    last_update: Duration,
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        RealClock::new()
+    }
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        RealClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl FixedTimestep {
+    /// Starts counting from the current time of the clock.
+    pub fn new(step: Duration, max_steps: u32, clock: &dyn Clock) -> Self {
+        FixedTimestep {
+            step: step.max(MIN_STEP),
+            max_steps: max_steps.max(1),
+            accumulator: Duration::from_secs(0),
+            last_update: clock.now(),
+        }
+    }
+
+    /// Changes the length of the following steps, the time already accumulated is kept.
+    pub fn set_step(&mut self, step: Duration) {
+        self.step = step.max(MIN_STEP);
+    }
+
+    /// Returns the number of steps to make for the time passed since the last update.
+    pub fn update(&mut self, clock: &dyn Clock) -> u32 {
+        let now = clock.now();
+        self.accumulator += now.checked_sub(self.last_update).unwrap_or_default();
+        self.last_update = now;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+
+            if steps == self.max_steps {
+                let remainder = self.accumulator.as_nanos() % self.step.as_nanos();
+                self.accumulator = Duration::from_nanos(remainder as u64);
+                break;
+            }
+        }
+
+        steps
+    }
+}
+
+#[cfg(test)]
+pub mod manual {
+    use super::Clock;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    /// Clock which only moves when told to, clones share the time.
+    #[derive(Clone, Default)]
+    pub struct ManualClock {
+        now: Rc<Cell<Duration>>,
+    }
+
+    impl ManualClock {
+        pub fn advance(&self, duration: Duration) {
+            self.now.set(self.now.get() + duration);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Duration {
+            self.now.get()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::manual::ManualClock;
+    use super::*;
+
+    fn ms(milliseconds: u64) -> Duration {
+        Duration::from_millis(milliseconds)
+    }
+
+    #[test]
+    fn test_steps_for_elapsed_time() {
+        let clock = ManualClock::default();
+        let mut timestep = FixedTimestep::new(ms(100), 10, &clock);
+
+        assert_eq!(timestep.update(&clock), 0);
+
+        clock.advance(ms(99));
+        assert_eq!(timestep.update(&clock), 0);
+
+        clock.advance(ms(1));
+        assert_eq!(timestep.update(&clock), 1);
+
+        clock.advance(ms(350));
+        assert_eq!(timestep.update(&clock), 3);
+
+        // the 50ms left over from before make up the next step
+        clock.advance(ms(50));
+        assert_eq!(timestep.update(&clock), 1);
+    }
+
+    #[test]
+    fn test_no_drift() {
+        let clock = ManualClock::default();
+        let mut timestep = FixedTimestep::new(ms(300), 10, &clock);
+
+        // frames which don't line up with the steps
+        let mut steps = 0;
+        for _ in 0..1000 {
+            clock.advance(ms(17));
+            steps += timestep.update(&clock);
+        }
+
+        assert_eq!(steps, 17_000 / 300);
+    }
+
+    #[test]
+    fn test_catch_up_limit() {
+        let clock = ManualClock::default();
+        let mut timestep = FixedTimestep::new(ms(100), 3, &clock);
+
+        clock.advance(ms(1050));
+        assert_eq!(timestep.update(&clock), 3);
+
+        // the stall is forgotten, only the part of a step is kept
+        clock.advance(ms(50));
+        assert_eq!(timestep.update(&clock), 1);
+        assert_eq!(timestep.update(&clock), 0);
+    }
+
+    #[test]
+    fn test_set_step() {
+        let clock = ManualClock::default();
+        let mut timestep = FixedTimestep::new(ms(100), 10, &clock);
+
+        clock.advance(ms(150));
+        assert_eq!(timestep.update(&clock), 1);
+
+        timestep.set_step(ms(50));
+        clock.advance(ms(50));
+        assert_eq!(timestep.update(&clock), 2);
+
+        timestep.set_step(Duration::from_secs(0));
+        clock.advance(ms(1));
+        assert_eq!(timestep.update(&clock), 10);
+    }
+}
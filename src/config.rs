@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Flat `key = value` configuration, one entry per line.
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub struct Config {
+    values: HashMap<String, (usize, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Syntax(usize),
+    InvalidValue(usize, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Syntax(line) => write!(f, "line {}: expected `key = value`", line),
+            ConfigError::InvalidValue(line, key) => {
+                write!(f, "line {}: invalid value for `{}`", line, key)
+            }
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut values = HashMap::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().map(str::trim).unwrap_or("");
+            let value = parts.next().map(str::trim);
+
+            match value {
+                Some(value) if !key.is_empty() => {
+                    values.insert(key.to_string(), (index + 1, value.to_string()));
+                }
+                _ => return Err(ConfigError::Syntax(index + 1)),
+            }
+        }
+
+        Ok(Config { values })
+    }
+
+    /// Returns the parsed value for `key`, or `None` when the key is absent.
+    pub fn get<T: FromStr>(&self, key: &str) -> Result<Option<T>, ConfigError> {
+        match self.values.get(key) {
+            Some((line, value)) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| ConfigError::InvalidValue(*line, key.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Error for a value which parsed but did not make sense.
+    pub fn invalid_value(&self, key: &str) -> ConfigError {
+        let line = self.values.get(key).map(|(line, _)| *line).unwrap_or(0);
+        ConfigError::InvalidValue(line, key.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse("# comment\n\nwidth = 24\n  tick_ms=300  \n").unwrap();
+        assert_eq!(config.get::<u32>("width"), Ok(Some(24)));
+        assert_eq!(config.get::<u64>("tick_ms"), Ok(Some(300)));
+        assert_eq!(config.get::<u32>("height"), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Config::parse("width = 24\nheight").err(),
+            Some(ConfigError::Syntax(2))
+        );
+
+        let config = Config::parse("width = wide").unwrap();
+        assert_eq!(
+            config.get::<u32>("width"),
+            Err(ConfigError::InvalidValue(1, "width".to_string()))
+        );
+    }
+}
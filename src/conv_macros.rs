@@ -37,4 +37,4 @@ impl From<&($num_type_from, $num_type_from)> for $type {
 
 )*
     };
-}
\ No newline at end of file
+}
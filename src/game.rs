@@ -1,35 +1,123 @@
-mod colors;
+mod achievements;
+mod effects;
+mod events;
 mod font;
+mod mode;
+mod occupancy;
+mod palette;
 mod pickup;
+mod portal;
+mod power_up;
+mod rng;
+mod save;
+mod seed;
 mod snake;
 mod snake_sprite;
+mod stats;
+mod text;
+mod toast;
 
-use crate::game::font::draw_string;
+pub use snake_sprite::SPRITE_SIZE;
+
+use crate::assets::{
+    AssetError, AssetSource, AssetWatcher, FONT_LAYOUT, FONT_SHEET, SETTINGS, SNAKE_SHEET,
+};
+use crate::audio::{self, Audio, AudioSettings, Sound};
+use crate::clock::{Clock, FixedTimestep, RealClock};
+use crate::config::{Config, ConfigError};
+use crate::point::Point;
 use crate::size::*;
 use crate::sprite_renderer::{
     GenericContext, SpriteRenderer, SpriteRendererSettings, SubSpriteRenderingContext,
 };
+use crate::storage::Storage;
 use opengl_graphics::OpenGL;
-use std::time::{Duration, Instant};
+use std::convert::TryFrom;
+use std::mem;
+use std::time::Duration;
 
-use opengl_graphics::Texture;
+use crate::sprite_batch::Sprite;
+use graphics::types::Color;
 use piston::input::*;
 
-use font::Font;
-use pickup::Pickup;
-use snake::{Direction, NewCell, Snake};
+use achievements::{Achievements, GameProgress, ACHIEVEMENTS, ACHIEVEMENTS_FILE};
+use effects::Effects;
+use events::{EventQueue, GameEvent};
+use font::{draw_string, Font};
+use mode::{Difficulty, GameMode, GameRules, GameSetup};
+use pickup::{Pickup, PickupKind};
+use portal::PortalPair;
+use power_up::{ActivePowerUps, PowerUp, POWER_UPS};
+use rand::Rng;
+use rng::GameRng;
+use save::{SaveError, Snapshot, SAVE_FILE};
+use seed::{normalize_seed_code, seeded_rng, today_seed_code, MAX_SEED_CODE_LENGTH};
+use snake::{Collision, Direction, NewCell, Snake, TailRule};
 use snake_sprite::SpriteData;
+use stats::{death_cause_title, format_play_time, GameEnd, GameSummary, Stats, STATS_FILE};
+use text::{draw_centered_paragraphs, draw_string_aligned, Alignment};
+use toast::Toasts;
+
+const DEFAULT_GAME_SIZE: (u32, u32) = (24, 16);
+const DEFAULT_SPRITE_SCALE: u32 = 3;
+const DEFAULT_TICK_DURATION_MS: u64 = 300;
+const DEFAULT_PROFILE: &str = "player";
+const MAX_FOOD_COUNT: u32 = 9;
+const MAX_PORTAL_PAIRS: u32 = 3;
+
+// every few points the snake gets faster, up to a limit
+const POINTS_PER_LEVEL: u32 = 5;
+const LEVEL_SPEEDUP: f64 = 0.9;
+const MAX_SPEEDUP_LEVEL: u32 = 10;
+
+const SLOW_MOTION_FACTOR: u32 = 2;
+const SHRINK_SEGMENTS: usize = 3;
+const POISON_SEGMENTS: usize = 2;
+const POISON_POINTS: u32 = 2;
+// hazards of all kinds on the field at once
+const MAX_HAZARDS: usize = 3;
+// the spikes roll on every other move of the snake
+const SPIKES_MOVE_EVERY: u64 = 2;
+// moves power-ups and hazards stay on the field for when they are not picked up
+const POWER_UP_LIFETIME: u32 = 40;
+const HAZARD_LIFETIME: u32 = 60;
+// cells taken by each active power-up on the bottom border, the symbol and the duration bar
+const POWER_UP_HUD_WIDTH: u32 = 5;
+
+// the countdown turns red for the last few seconds
+const COUNTDOWN_WARNING: Duration = Duration::from_secs(10);
+
+// after a stall the snake makes at most this many moves at once
+const MAX_CATCH_UP_TICKS: u32 = 3;
+
+// the border, the score line and the initial snake have to fit
+const MIN_GAME_SIZE: (u32, u32) = (9, 5);
+// larger boards no longer fit on a screen
+const MAX_GAME_SIZE: (u32, u32) = (200, 200);
 
-const FRAME_DURATION: Duration = Duration::from_millis(300);
+// the snake flashes and crumbles for a while after the crash before the game is over
+const DEATH_DURATION: Duration = Duration::from_millis(1200);
+const DEATH_FLASH_PERIOD: Duration = Duration::from_millis(150);
 
 enum GameState {
+    Menu(MenuState),
+    SeedEntry(SeedEntryState),
+    Stats,
     Playing(PlayingState),
-    GameOver(u32),
+    Dying(DyingState),
+    GameOver(GameOverState),
 }
 
 enum GameFlow {
-    StartNew,
-    ShowGameOver(u32),
+    StartNew(GameSetup),
+    Continue,
+    Die(Collision),
+    Win,
+    TimeUp,
+    ShowGameOver(GameSummary),
+    ShowMenu,
+    ShowSeedEntry,
+    ShowStats,
 }
 
 pub struct Game {
@@ -37,35 +125,234 @@ pub struct Game {
     settings: GameSettings,
     sprites: SpriteData,
     state: GameState,
+    effects: Effects,
+    events: EventQueue,
+    audio: Audio,
+    clock: Box<dyn Clock>,
+    storage: Option<Storage>,
+    profile: Option<Storage>,
+    stats: Stats,
+    achievements: Achievements,
+    toasts: Toasts,
+    assets: AssetSource,
+    asset_watcher: Option<AssetWatcher>,
 }
 
 pub struct GameSettings {
     opengl: OpenGL,
     sprite_size: u32,
     sprite_scale: u32,
+    fullscreen: bool,
     game_size: Size,
+    tick_duration: Duration,
+    difficulty: Difficulty,
+    mode: GameMode,
+    tail_rule: TailRule,
+    rules: GameRules,
+    audio: AudioSettings,
+    profile: String,
 }
 
 impl GameSettings {
-    pub fn new<S>(opengl: OpenGL, sprite_size: u32, sprite_scale: u32, game_size: S) -> GameSettings
-    where
-        S: Into<Size>,
-    {
-        GameSettings {
+    /// Reads the settings file from the assets.
+    pub fn load(
+        opengl: OpenGL,
+        sprite_size: u32,
+        assets: &AssetSource,
+    ) -> Result<GameSettings, AssetError> {
+        let config = assets.load_config(SETTINGS)?;
+        GameSettings::from_config(opengl, sprite_size, &config)
+            .map_err(|error| AssetError::Config(SETTINGS.to_string(), error))
+    }
+
+    fn from_config(
+        opengl: OpenGL,
+        sprite_size: u32,
+        config: &Config,
+    ) -> Result<GameSettings, ConfigError> {
+        let (default_width, default_height) = DEFAULT_GAME_SIZE;
+        let (min_width, min_height) = MIN_GAME_SIZE;
+        let (max_width, max_height) = MAX_GAME_SIZE;
+
+        let width: u32 = config.get("width")?.unwrap_or(default_width);
+        let height: u32 = config.get("height")?.unwrap_or(default_height);
+        let sprite_scale: u32 = config.get("sprite_scale")?.unwrap_or(DEFAULT_SPRITE_SCALE);
+        let tick_ms: u64 = config.get("tick_ms")?.unwrap_or(DEFAULT_TICK_DURATION_MS);
+        let fullscreen: bool = config.get("fullscreen")?.unwrap_or(false);
+        let difficulty_key: String = config
+            .get("difficulty")?
+            .unwrap_or_else(|| Difficulty::Normal.key().to_string());
+        let difficulty = Difficulty::from_key(&difficulty_key)
+            .ok_or_else(|| config.invalid_value("difficulty"))?;
+        let mode_key: String = config
+            .get("mode")?
+            .unwrap_or_else(|| GameMode::Classic.key().to_string());
+        // the daily challenge has its own item on the menu
+        let mode = GameMode::from_key(&mode_key)
+            .filter(|mode| *mode != GameMode::Daily)
+            .ok_or_else(|| config.invalid_value("mode"))?;
+        let tail_rule_key: String = config
+            .get("tail_rule")?
+            .unwrap_or_else(|| TailRule::default().key().to_string());
+        let tail_rule =
+            TailRule::from_key(&tail_rule_key).ok_or_else(|| config.invalid_value("tail_rule"))?;
+        let default_rules = GameRules::default();
+        let food_count: u32 = config
+            .get("food_count")?
+            .unwrap_or(default_rules.food_count);
+        let power_up_chance: f64 = config
+            .get("power_up_chance")?
+            .unwrap_or(default_rules.power_up_chance);
+        let hazard_chance: f64 = config
+            .get("hazard_chance")?
+            .unwrap_or(default_rules.hazard_chance);
+        // no lifetime is written as zero
+        let cherry_lifetime: u32 = config
+            .get("cherry_lifetime")?
+            .unwrap_or_else(|| default_rules.cherry_lifetime.unwrap_or(0));
+        let wandering_cherries: bool = config
+            .get("wandering_cherries")?
+            .unwrap_or(default_rules.wandering_cherries);
+        let portal_pairs: u32 = config
+            .get("portal_pairs")?
+            .unwrap_or(default_rules.portal_pairs);
+        let profile: String = config
+            .get("profile")?
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+
+        // the name becomes a directory
+        let valid_profile = !profile.is_empty()
+            && profile
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || "-_".contains(character));
+        if !valid_profile {
+            return Err(config.invalid_value("profile"));
+        }
+
+        Ok(GameSettings {
             opengl,
             sprite_size,
-            sprite_scale,
-            game_size: game_size.into(),
+            sprite_scale: sprite_scale.max(1),
+            fullscreen,
+            game_size: (
+                width.clamp(min_width, max_width),
+                height.clamp(min_height, max_height),
+            )
+                .into(),
+            tick_duration: Duration::from_millis(tick_ms.max(1)),
+            difficulty,
+            mode,
+            tail_rule,
+            rules: GameRules {
+                food_count: food_count.clamp(1, MAX_FOOD_COUNT),
+                power_up_chance: power_up_chance.clamp(0.0, 1.0),
+                hazard_chance: hazard_chance.clamp(0.0, 1.0),
+                cherry_lifetime: Some(cherry_lifetime).filter(|lifetime| *lifetime > 0),
+                wandering_cherries,
+                portal_pairs: portal_pairs.min(MAX_PORTAL_PAIRS),
+                ..default_rules
+            },
+            audio: AudioSettings::from_config(config)?,
+            profile,
+        })
+    }
+
+    /// Initial size of the window, the field is rescaled to fit when the window is resized.
+    pub fn window_size(&self) -> [u32; 2] {
+        let sprite_size = self.sprite_size * self.sprite_scale;
+        [
+            self.game_size.width * sprite_size,
+            self.game_size.height * sprite_size,
+        ]
+    }
+
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Board a new game is played on. Seeded games are always played on the default one, so that
+    /// a code places the pickups the same way for everybody.
+    fn game_size_for(&self, setup: &GameSetup) -> Size {
+        match setup.seed {
+            Some(_) => DEFAULT_GAME_SIZE.into(),
+            None => self.game_size,
+        }
+    }
+
+    /// Rules a new game is played by, the default ones for seeded games.
+    fn rules_for(&self, setup: &GameSetup) -> GameRules {
+        match setup.seed {
+            Some(_) => GameRules::default(),
+            None => self.rules,
+        }
+    }
+
+    fn tail_rule_for(&self, setup: &GameSetup) -> TailRule {
+        match setup.seed {
+            Some(_) => TailRule::default(),
+            None => self.tail_rule,
         }
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum MenuItem {
+    Continue,
+    NewGame,
+    Daily,
+    EnterSeed,
+    Stats,
+}
+
+pub struct MenuState {
+    items: Vec<MenuItem>,
+    selected: usize,
+}
+
+/// Seed code being typed in, to replay somebody else's game.
+pub struct SeedEntryState {
+    code: String,
+}
+
 pub struct PlayingState {
+    mode: GameMode,
+    seed: Option<String>,
+    difficulty: Difficulty,
     game_size: Size,
     snake: Snake,
-    last_move_instant: Instant,
-    cherry_pickup: Pickup,
+    timestep: FixedTimestep,
+    /// Cherries on the field, each one is replaced on its own when eaten, at most one power-up
+    /// and up to `MAX_HAZARDS` hazards.
+    pickups: Vec<Pickup>,
+    power_ups: ActivePowerUps,
+    rules: GameRules,
     score: u32,
+    cherries_eaten: u32,
+    level: u32,
+    // moves made so far
+    ticks: u64,
+    // play time before the game was continued, and the clock time when it was started or continued
+    earlier_play_time: Duration,
+    started_at: Duration,
+    rng: GameRng,
+}
+
+/// The board frozen at the moment of the crash.
+pub struct DyingState {
+    playing_state: PlayingState,
+    summary: GameSummary,
+    started_at: Duration,
+}
+
+impl DyingState {
+    fn elapsed(&self, now: Duration) -> Duration {
+        now.checked_sub(self.started_at).unwrap_or_default()
+    }
+}
+
+pub struct GameOverState {
+    summary: GameSummary,
+    new_best: bool,
 }
 
 fn shrink_context<C>(
@@ -97,72 +384,569 @@ where
     shrink_context(context, (1, 1, 1, 1))
 }
 
-fn draw_score<'a, C>(context: &'a mut C, score: u32, font: &Font)
+/// Draws the score line, the level in the middle gives way to the countdown when the time of
+/// the game is limited.
+fn draw_score<C>(context: &mut C, score: u32, level: u32, time_left: Option<Duration>, font: &Font)
 where
     C: GenericContext,
 {
-    let text = format!("score: {}", score);
-    draw_string(context, text, (0, 0), font);
+    draw_string_aligned(context, "score:", 0, Alignment::Left, palette::WHITE, font);
+    let (middle, color) = match time_left {
+        Some(time_left) if time_left <= COUNTDOWN_WARNING => {
+            (format_countdown(time_left), palette::RED)
+        }
+        Some(time_left) => (format_countdown(time_left), palette::WHITE),
+        None => (format!("lv {}", level), palette::GRAY),
+    };
+    draw_string_aligned(context, middle, 0, Alignment::Center, color, font);
+    draw_string_aligned(
+        context,
+        score.to_string(),
+        0,
+        Alignment::Right,
+        palette::YELLOW,
+        font,
+    );
 }
 
-fn draw_border<'a, C>(context: &'a mut C, texture: &Texture)
+/// Draws the symbols of the active power-ups over the bottom border, each with a bar of the
+/// moves it has left.
+fn draw_power_ups<C>(context: &mut C, power_ups: &ActivePowerUps, solid: &Sprite, font: &Font)
 where
     C: GenericContext,
 {
-    let size = context.size();
+    let row = context.height() as i32 - 1;
+    let fitting = (context.width() - 1) / POWER_UP_HUD_WIDTH;
+
+    for (index, (power_up, moves)) in power_ups.remaining().take(fitting as usize).enumerate() {
+        let column = 1 + index as u32 * POWER_UP_HUD_WIDTH;
+        let left = (moves as f64 / power_up.duration().unwrap_or(moves) as f64).min(1.0);
+        let bar_width = (POWER_UP_HUD_WIDTH - 2) as f64;
+
+        context.draw_sprite_at(
+            [column as f64, row as f64],
+            [(POWER_UP_HUD_WIDTH - 1) as f64, 1.0],
+            solid,
+            palette::BLACK,
+        );
+        draw_string(
+            context,
+            power_up.symbol(),
+            (column as i32, row),
+            power_up.color(),
+            font,
+        );
+        context.draw_sprite_at(
+            [column as f64 + 1.0, row as f64 + 0.25],
+            [bar_width * left, 0.5],
+            solid,
+            power_up.color(),
+        );
+    }
+}
+
+fn draw_border<C>(context: &mut C, sprite: &Sprite)
+where
+    C: GenericContext,
+{
+    let width = context.width();
+    let height = context.height();
+
+    for x in 0..width {
+        context.draw_sprite((x as i32, 0), sprite);
+        context.draw_sprite((x as i32, height as i32 - 1), sprite);
+    }
 
-    for x in 0..size.width {
-        context.draw_sprite((x as i32, 0), texture);
-        context.draw_sprite((x as i32, size.height as i32 - 1), texture);
+    for y in 1..height - 1 {
+        context.draw_sprite((0, y), sprite);
+        context.draw_sprite((width - 1, y), sprite);
     }
+}
+
+/// Formats the time as `m:ss`, started seconds count as whole ones so that zero shows only when
+/// the time is up.
+fn format_countdown(time_left: Duration) -> String {
+    let seconds = (time_left.as_millis() as u64).div_ceil(1000);
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn level_for_score(score: u32) -> u32 {
+    score / POINTS_PER_LEVEL + 1
+}
 
-    for y in 1..size.height - 1 {
-        context.draw_sprite((0, y), texture);
-        context.draw_sprite((size.width - 1, y), texture);
+/// Time between two moves, shorter on higher levels.
+fn level_tick_duration(tick_duration: Duration, level: u32) -> Duration {
+    let speedup = LEVEL_SPEEDUP.powi(level.min(MAX_SPEEDUP_LEVEL) as i32 - 1);
+    Duration::from_secs_f64(tick_duration.as_secs_f64() * speedup)
+}
+
+fn describe_death(cause: &Collision) -> &'static str {
+    match cause {
+        Collision::Border(_) => "You hit the wall",
+        Collision::Body(_) => "You bit yourself",
+        Collision::Hazard(_) => "You hit a hazard",
     }
 }
 
-fn draw_game_over_screen<'a, C>(context: &'a mut C, score: u32, font: &Font)
+/// Number of body parts still shown while the snake crumbles from the tail, the head stays.
+fn remaining_parts(length: usize, elapsed: Duration, duration: Duration) -> usize {
+    let progress = (elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.0);
+    let crumbled = ((length - 1) as f64 * progress) as usize;
+    length - crumbled
+}
+
+/// Clearing the board is worth a point for every cell of the field.
+fn victory_bonus(field_size: Size) -> u32 {
+    field_size.width * field_size.height
+}
+
+fn final_score_paragraphs(game_over_state: &GameOverState) -> Vec<(String, Color)> {
+    let summary = &game_over_state.summary;
+
+    vec![
+        (
+            match game_over_state.new_best {
+                true => format!("New best score {}", summary.score),
+                false => format!("You scored {}", summary.score),
+            },
+            palette::YELLOW,
+        ),
+        (summary.mode.title().to_string(), palette::WHITE),
+        (
+            match &summary.seed {
+                Some(seed) => format!("Seed {}", seed),
+                None => String::new(),
+            },
+            palette::GRAY,
+        ),
+        (
+            "Press SPACE to restart or ESC for the menu".to_string(),
+            palette::GRAY,
+        ),
+    ]
+}
+
+fn draw_game_over_screen<C>(
+    context: &mut C,
+    game_over_state: &GameOverState,
+    collision: &Collision,
+    font: &Font,
+) where
+    C: GenericContext,
+{
+    let mut paragraphs = vec![
+        ("Game Over".to_string(), palette::RED),
+        (describe_death(collision).to_string(), palette::WHITE),
+    ];
+    paragraphs.extend(final_score_paragraphs(game_over_state));
+
+    draw_centered_paragraphs(context, &paragraphs, font);
+}
+
+fn draw_victory_screen<C>(context: &mut C, game_over_state: &GameOverState, font: &Font)
+where
+    C: GenericContext,
+{
+    let bonus = victory_bonus(field_size(game_over_state.summary.game_size));
+    let mut paragraphs = vec![
+        ("Board cleared!".to_string(), palette::YELLOW),
+        (
+            format!("The snake filled the board, bonus {}", bonus),
+            palette::WHITE,
+        ),
+    ];
+    paragraphs.extend(final_score_paragraphs(game_over_state));
+
+    draw_centered_paragraphs(context, &paragraphs, font);
+}
+
+fn draw_time_up_screen<C>(context: &mut C, game_over_state: &GameOverState, font: &Font)
+where
+    C: GenericContext,
+{
+    let mut paragraphs = vec![
+        ("Time's up!".to_string(), palette::YELLOW),
+        (
+            format!(
+                "The snake ate {} cherries",
+                game_over_state.summary.cherries_eaten
+            ),
+            palette::WHITE,
+        ),
+    ];
+    paragraphs.extend(final_score_paragraphs(game_over_state));
+
+    draw_centered_paragraphs(context, &paragraphs, font);
+}
+
+fn draw_seed_entry<C>(context: &mut C, seed_entry_state: &SeedEntryState, font: &Font)
+where
+    C: GenericContext,
+{
+    draw_centered_paragraphs(
+        context,
+        &[
+            ("Enter seed code".to_string(), palette::WHITE),
+            (format!("{}_", seed_entry_state.code), palette::YELLOW),
+            (
+                "Press ENTER to play or ESC to go back".to_string(),
+                palette::GRAY,
+            ),
+        ],
+        font,
+    );
+}
+
+fn draw_stats<C>(context: &mut C, stats: &Stats, achievements: &Achievements, font: &Font)
 where
     C: GenericContext,
 {
-    draw_string(context, "Game Over".to_uppercase(), (8, 2), font);
-    draw_string(
+    let mut rows = vec![
+        ("Games played".to_string(), stats.games_played.to_string()),
+        (
+            "Cherries eaten".to_string(),
+            stats.cherries_eaten.to_string(),
+        ),
+        ("Longest snake".to_string(), stats.longest_snake.to_string()),
+        ("Play time".to_string(), format_play_time(stats.play_time)),
+        (
+            "Boards cleared".to_string(),
+            stats.boards_cleared.to_string(),
+        ),
+        (
+            "Achievements".to_string(),
+            format!("{}/{}", achievements.unlocked_count(), ACHIEVEMENTS.len()),
+        ),
+    ];
+    rows.extend(
+        stats
+            .deaths
+            .iter()
+            .map(|(cause, count)| (death_cause_title(cause).to_string(), count.to_string())),
+    );
+    rows.extend(
+        stats
+            .best_scores
+            .iter()
+            .map(|((mode, width, height), score)| {
+                (
+                    format!("Best {} {}x{}", mode.title(), width, height),
+                    score.to_string(),
+                )
+            }),
+    );
+
+    draw_string_aligned(
+        context,
+        "Statistics",
+        0,
+        Alignment::Center,
+        palette::WHITE,
+        font,
+    );
+
+    let mut list_context = shrink_context(context, (1, 2, 0, 1));
+    for (row, (label, value)) in rows.iter().enumerate() {
+        let row = row as i32;
+        draw_string_aligned(
+            &mut list_context,
+            label,
+            row,
+            Alignment::Left,
+            palette::GRAY,
+            font,
+        );
+        draw_string_aligned(
+            &mut list_context,
+            value,
+            row,
+            Alignment::Right,
+            palette::YELLOW,
+            font,
+        );
+    }
+
+    let bottom = context.height() as i32 - 1;
+    draw_string_aligned(
         context,
-        format!("You scored {}", score).to_uppercase(),
-        (4, 4),
+        "Press ESC to go back",
+        bottom,
+        Alignment::Center,
+        palette::GRAY,
         font,
     );
-    draw_string(context, "Press SPACE to restart", (1, 6), font);
 }
 
-fn new_cherry_pickup<S>(field_size: S, snake: &Snake) -> Pickup
+fn draw_menu<C>(context: &mut C, menu_state: &MenuState, font: &Font)
 where
-    S: Into<Size>,
+    C: GenericContext,
 {
-    Pickup::new_cherry(field_size, &snake.get_occupied_cells())
-        .expect("Couldn't generate the first cherry")
+    let mut paragraphs = vec![("Snake".to_string(), palette::WHITE)];
+
+    for (index, item) in menu_state.items.iter().enumerate() {
+        let label = match item {
+            MenuItem::Continue => "Continue",
+            MenuItem::NewGame => "New game",
+            MenuItem::Daily => "Daily challenge",
+            MenuItem::EnterSeed => "Enter seed",
+            MenuItem::Stats => "Statistics",
+        };
+
+        paragraphs.push(match index == menu_state.selected {
+            true => (format!("> {} <", label), palette::YELLOW),
+            false => (label.to_string(), palette::GRAY),
+        });
+    }
+
+    draw_centered_paragraphs(context, &paragraphs, font);
 }
 
-impl GameState {
-    fn new_playing<S: Into<Size>>(game_size: S) -> Self {
+/// Part of the board inside the border and below the score line.
+fn field_size(game_size: Size) -> Size {
+    (game_size.width - 2, game_size.height - 3).into()
+}
+
+/// Places a cherry on a cell free of the snake and the other pickups, returns `None` when there
+/// is no such cell.
+fn new_cherry_pickup(
+    snake: &Snake,
+    pickups: &[Pickup],
+    rules: &GameRules,
+    rng: &mut GameRng,
+) -> Option<Pickup> {
+    Pickup::new_cherry(snake.occupancy(), pickups, rng).map(|pickup| {
+        pickup
+            .with_lifetime(rules.cherry_lifetime)
+            .wandering(rules.wandering_cherries)
+    })
+}
+
+impl MenuState {
+    /// Offers to continue only when there is a saved game.
+    fn new(can_continue: bool) -> Self {
+        let mut items = vec![
+            MenuItem::NewGame,
+            MenuItem::Daily,
+            MenuItem::EnterSeed,
+            MenuItem::Stats,
+        ];
+        if can_continue {
+            items.insert(0, MenuItem::Continue);
+        }
+
+        MenuState { items, selected: 0 }
+    }
+
+    fn move_selection(&mut self, forward: bool) {
+        let count = self.items.len();
+        self.selected = match forward {
+            true => (self.selected + 1) % count,
+            false => (self.selected + count - 1) % count,
+        };
+    }
+}
+
+impl PlayingState {
+    fn new<S: Into<Size>>(
+        game_size: S,
+        setup: GameSetup,
+        difficulty: Difficulty,
+        rules: GameRules,
+        tick_duration: Duration,
+        clock: &dyn Clock,
+        mut rng: GameRng,
+    ) -> Self {
         let game_size: Size = game_size.into();
-        let field_size = (game_size.width - 2, game_size.height - 3);
-        let snake = Snake::new((4, 0), 5, field_size);
+        let field_size = field_size(game_size);
+        let rules = rules.for_mode(setup.mode);
+        let mut snake = Snake::new((4, 0), 5, field_size);
+        snake.set_color(palette::snake_color(0));
+
+        // the portals go first, so that the cherries keep off them
+        let mut portals = Vec::new();
+        for _ in 0..rules.portal_pairs {
+            match PortalPair::new_random(snake.occupancy(), &portals, &mut rng) {
+                Some(portal) => portals.push(portal),
+                None => break,
+            }
+        }
+        assert!(
+            snake.set_portals(portals),
+            "The portals are placed away from the snake and each other"
+        );
 
-        let cherry_pickup = new_cherry_pickup(field_size, &snake);
+        let mut pickups = Vec::new();
+        for _ in 0..rules.food_count {
+            match new_cherry_pickup(&snake, &pickups, &rules, &mut rng) {
+                Some(pickup) => pickups.push(pickup),
+                None => break,
+            }
+        }
+        assert!(
+            rules.food_count == 0 || !pickups.is_empty(),
+            "The field has room for the first cherry"
+        );
 
-        GameState::Playing(PlayingState {
+        PlayingState {
+            mode: setup.mode,
+            seed: setup.seed,
+            difficulty,
             game_size,
             snake,
-            cherry_pickup,
-            last_move_instant: Instant::now(),
+            pickups,
+            power_ups: ActivePowerUps::new(),
+            rules,
+            timestep: FixedTimestep::new(
+                difficulty.tick_duration(tick_duration),
+                MAX_CATCH_UP_TICKS,
+                clock,
+            ),
             score: 0,
+            cherries_eaten: 0,
+            level: 1,
+            ticks: 0,
+            earlier_play_time: Duration::from_secs(0),
+            started_at: clock.now(),
+            rng,
+        }
+    }
+
+    fn play_time(&self, now: Duration) -> Duration {
+        self.earlier_play_time + now.checked_sub(self.started_at).unwrap_or_default()
+    }
+
+    fn summary(&self, end: GameEnd, now: Duration) -> GameSummary {
+        GameSummary {
+            mode: self.mode,
+            seed: self.seed.clone(),
+            game_size: self.game_size,
+            score: self.score,
+            cherries_eaten: self.cherries_eaten,
+            snake_length: self.snake.length(),
+            play_time: self.play_time(now),
+            end,
+        }
+    }
+
+    fn snapshot(&self, now: Duration) -> Snapshot {
+        Snapshot {
+            mode: self.mode,
+            seed: self.seed.clone(),
+            difficulty: self.difficulty,
+            game_size: self.game_size,
+            snake: self.snake.parts().cloned().collect(),
+            direction: self.snake.direction(),
+            next_direction: self.snake.next_direction(),
+            growth: self.snake.growth(),
+            tail_rule: self.snake.tail_rule(),
+            rules: self.rules,
+            pickups: self.pickups.clone(),
+            portals: self.snake.portals().to_vec(),
+            free_cells: self.snake.occupancy().free_cells().to_vec(),
+            power_ups: self.power_ups.remaining().collect(),
+            score: self.score,
+            cherries_eaten: self.cherries_eaten,
+            level: self.level,
+            ticks: self.ticks,
+            play_time: self.play_time(now),
+            rng_state: self.rng.state(),
+        }
+    }
+
+    /// Continues a saved game, the time until the next move starts over.
+    fn restore(snapshot: Snapshot, tick_duration: Duration, clock: &dyn Clock) -> Self {
+        let field_size = field_size(snapshot.game_size);
+        let mut snake = Snake::from_parts(
+            snapshot.snake,
+            snapshot.direction,
+            snapshot.next_direction,
+            field_size,
+        )
+        .expect("The snake was checked when the save was parsed");
+        snake.set_color(palette::snake_color(0));
+        snake.set_tail_rule(snapshot.tail_rule);
+        for _ in 0..snapshot.growth {
+            snake.grow();
+        }
+        assert!(
+            snake.set_portals(snapshot.portals),
+            "The portals were checked when the save was parsed"
+        );
+        assert!(
+            snapshot.free_cells.is_empty() || snake.set_free_order(&snapshot.free_cells),
+            "The free cells were checked when the save was parsed"
+        );
+
+        PlayingState {
+            mode: snapshot.mode,
+            seed: snapshot.seed,
+            difficulty: snapshot.difficulty,
+            game_size: snapshot.game_size,
+            snake,
+            pickups: snapshot.pickups,
+            power_ups: ActivePowerUps::from_remaining(snapshot.power_ups),
+            rules: snapshot.rules,
+            timestep: FixedTimestep::new(
+                snapshot.difficulty.tick_duration(tick_duration),
+                MAX_CATCH_UP_TICKS,
+                clock,
+            ),
+            score: snapshot.score,
+            cherries_eaten: snapshot.cherries_eaten,
+            level: snapshot.level,
+            ticks: snapshot.ticks,
+            earlier_play_time: snapshot.play_time,
+            started_at: clock.now(),
+            rng: GameRng::from_state(snapshot.rng_state),
+        }
+    }
+}
+
+impl GameState {
+    fn new_menu(can_continue: bool) -> Self {
+        GameState::Menu(MenuState::new(can_continue))
+    }
+
+    fn new_playing<S: Into<Size>>(
+        game_size: S,
+        setup: GameSetup,
+        difficulty: Difficulty,
+        rules: GameRules,
+        tick_duration: Duration,
+        clock: &dyn Clock,
+    ) -> Self {
+        let rng = match &setup.seed {
+            Some(seed) => seeded_rng(seed),
+            None => GameRng::from_entropy(),
+        };
+
+        GameState::Playing(PlayingState::new(
+            game_size,
+            setup,
+            difficulty,
+            rules,
+            tick_duration,
+            clock,
+            rng,
+        ))
+    }
+
+    fn new_dying(playing_state: PlayingState, collision: Collision, clock: &dyn Clock) -> Self {
+        let now = clock.now();
+        GameState::Dying(DyingState {
+            summary: playing_state.summary(GameEnd::Crashed(collision), now),
+            playing_state,
+            started_at: now,
         })
     }
 
-    fn new_game_over(score: u32) -> Self {
-        GameState::GameOver(score)
+    fn new_game_over(summary: GameSummary, new_best: bool) -> Self {
+        GameState::GameOver(GameOverState { summary, new_best })
+    }
+
+    /// Whether the state takes typed letters, which then must not trigger the shortcuts.
+    fn takes_text(&self) -> bool {
+        matches!(self, GameState::SeedEntry(_))
     }
 }
 
@@ -171,121 +955,784 @@ fn render_playing(
     sprite_data: &SpriteData,
     sprite_renderer: &mut SpriteRenderer,
     playing_state: &PlayingState,
+    dying_state: Option<&DyingState>,
+    effects: &Effects,
+    now: Duration,
     args: &RenderArgs,
 ) {
     let viewport = args.viewport();
 
-    let brick_texture = &sprite_data.brick;
+    let brick_sprite = &sprite_data.brick;
     let score = playing_state.score;
+    let level = playing_state.level;
+    let time_left = playing_state
+        .rules
+        .time_limit
+        .map(|time_limit| time_limit.saturating_sub(playing_state.play_time(now)));
 
-    sprite_renderer.draw(viewport, |context| {
-        context.clear(colors::BLACK);
+    sprite_renderer.draw(viewport, &sprite_data.atlas, |context| {
+        context.clear(palette::BLACK);
+        context.translate(effects.shake_offset());
 
-        draw_score(context, score, &sprite_data.font);
+        draw_score(context, score, level, time_left, &sprite_data.font);
 
         let mut border_context = get_border_context(context);
-        draw_border(&mut border_context, brick_texture);
+        draw_border(&mut border_context, brick_sprite);
+        draw_power_ups(
+            &mut border_context,
+            &playing_state.power_ups,
+            &sprite_data.solid,
+            &sprite_data.font,
+        );
 
         let mut playing_field_context = get_playing_field_context(&mut border_context);
 
-        playing_state
-            .snake
-            .render(&mut playing_field_context, &sprite_data);
-        playing_state
-            .cherry_pickup
-            .render(&mut playing_field_context, &sprite_data);
+        for (index, portal) in playing_state.snake.portals().iter().enumerate() {
+            portal.render(
+                &mut playing_field_context,
+                palette::portal_color(index),
+                &sprite_data.font,
+            );
+        }
+        for pickup in &playing_state.pickups {
+            pickup.render(&mut playing_field_context, sprite_data);
+        }
+
+        match dying_state {
+            Some(dying_state) => render_death(
+                &mut playing_field_context,
+                sprite_data,
+                dying_state,
+                dying_state.elapsed(now),
+            ),
+            None => playing_state
+                .snake
+                .render(&mut playing_field_context, sprite_data),
+        }
+
+        effects.render(
+            &mut playing_field_context,
+            &sprite_data.solid,
+            &sprite_data.font,
+        );
+    });
+}
+
+/// Flashes the crumbling snake and the cell it crashed into.
+fn render_death<C>(
+    context: &mut C,
+    sprite_data: &SpriteData,
+    dying_state: &DyingState,
+    elapsed: Duration,
+) where
+    C: GenericContext,
+{
+    let snake = &dying_state.playing_state.snake;
+
+    let color = palette::blink(
+        palette::DEATH_FLASH,
+        palette::with_alpha(snake.color(), 0.4),
+        elapsed,
+        DEATH_FLASH_PERIOD,
+    );
+    let parts = remaining_parts(snake.length(), elapsed, DEATH_DURATION);
+    snake.render_parts(context, sprite_data, color, parts);
+
+    // border cells are drawn over the bricks, which lie just outside of the field
+    let (cell, highlight) = match dying_state.summary.end {
+        GameEnd::Crashed(Collision::Border(cell)) => (cell, palette::YELLOW),
+        GameEnd::Crashed(Collision::Body(cell)) | GameEnd::Crashed(Collision::Hazard(cell)) => {
+            (cell, palette::RED)
+        }
+        // nothing was hit
+        GameEnd::BoardCleared | GameEnd::TimeUp => return,
+    };
+    let highlight = palette::blink(
+        palette::with_alpha(highlight, 0.7),
+        palette::with_alpha(highlight, 0.2),
+        elapsed,
+        DEATH_FLASH_PERIOD,
+    );
+    context.draw_sprite_at(
+        [cell.x as f64, cell.y as f64],
+        [1.0, 1.0],
+        &sprite_data.solid,
+        highlight,
+    );
+}
+
+fn render_menu(
+    sprite_data: &SpriteData,
+    sprite_renderer: &mut SpriteRenderer,
+    menu_state: &MenuState,
+    args: &RenderArgs,
+) {
+    let viewport = args.viewport();
+
+    sprite_renderer.draw(viewport, &sprite_data.atlas, |context| {
+        context.clear(palette::BLACK);
+
+        draw_menu(context, menu_state, &sprite_data.font);
+    });
+}
+
+fn render_seed_entry(
+    sprite_data: &SpriteData,
+    sprite_renderer: &mut SpriteRenderer,
+    seed_entry_state: &SeedEntryState,
+    args: &RenderArgs,
+) {
+    let viewport = args.viewport();
+
+    sprite_renderer.draw(viewport, &sprite_data.atlas, |context| {
+        context.clear(palette::BLACK);
+
+        draw_seed_entry(context, seed_entry_state, &sprite_data.font);
+    });
+}
+
+fn render_stats(
+    sprite_data: &SpriteData,
+    sprite_renderer: &mut SpriteRenderer,
+    stats: &Stats,
+    achievements: &Achievements,
+    args: &RenderArgs,
+) {
+    let viewport = args.viewport();
+
+    sprite_renderer.draw(viewport, &sprite_data.atlas, |context| {
+        context.clear(palette::BLACK);
+
+        draw_stats(context, stats, achievements, &sprite_data.font);
+    });
+}
+
+/// Draws the toasts over whatever the screen shows.
+fn render_toasts(
+    sprite_data: &SpriteData,
+    sprite_renderer: &mut SpriteRenderer,
+    toasts: &Toasts,
+    args: &RenderArgs,
+) {
+    let viewport = args.viewport();
+
+    sprite_renderer.draw(viewport, &sprite_data.atlas, |context| {
+        toasts.render(context, &sprite_data.solid, &sprite_data.font);
     });
 }
 
 fn render_game_over(
     sprite_data: &SpriteData,
     sprite_renderer: &mut SpriteRenderer,
-    score: u32,
+    game_over_state: &GameOverState,
     args: &RenderArgs,
 ) {
     let viewport = args.viewport();
 
     let font = &sprite_data.font;
 
-    sprite_renderer.draw(viewport, |context| {
-        context.clear(colors::BLACK);
+    sprite_renderer.draw(viewport, &sprite_data.atlas, |context| {
+        context.clear(palette::BLACK);
 
-        draw_game_over_screen(context, score, &font);
+        match &game_over_state.summary.end {
+            GameEnd::Crashed(collision) => {
+                draw_game_over_screen(context, game_over_state, collision, font)
+            }
+            GameEnd::BoardCleared => draw_victory_screen(context, game_over_state, font),
+            GameEnd::TimeUp => draw_time_up_screen(context, game_over_state, font),
+        }
     });
 }
 
-fn update_playing(playing_state: &mut PlayingState) -> Option<GameFlow> {
-    // move or grow the snake
-    if playing_state.last_move_instant.elapsed() >= FRAME_DURATION {
-        playing_state.last_move_instant = Instant::now();
-        match playing_state.snake.advance(&playing_state.cherry_pickup) {
-            Ok(cell) => {
-                if let NewCell::Pickup = cell {
-                    playing_state.score += 1;
-                    // recreate the pickup
-                    let field_size = (
-                        playing_state.game_size.width - 2,
-                        playing_state.game_size.height - 3,
-                    );
-                    playing_state.cherry_pickup =
-                        new_cherry_pickup(field_size, &playing_state.snake);
-                }
-            }
-            Err(_) => {
-                // smashed the head
-                return Some(GameFlow::ShowGameOver(playing_state.score));
-            }
+fn update_playing(
+    playing_state: &mut PlayingState,
+    tick_duration: Duration,
+    clock: &dyn Clock,
+    events: &mut EventQueue,
+) -> Option<GameFlow> {
+    if let Some(time_limit) = playing_state.rules.time_limit {
+        if playing_state.play_time(clock.now()) >= time_limit {
+            events.push(GameEvent::TimeUp {
+                position: playing_state.snake.head(),
+            });
+            return Some(GameFlow::TimeUp);
         }
     }
 
-    None
-}
+    let step = level_tick_duration(
+        playing_state.difficulty.tick_duration(tick_duration),
+        playing_state.level,
+    );
+    playing_state.timestep.set_step(
+        match playing_state.power_ups.is_active(PowerUp::SlowMotion) {
+            true => step * SLOW_MOTION_FACTOR,
+            false => step,
+        },
+    );
 
-fn handle_key_press_playing(playing_state: &mut PlayingState, key: &Key) -> Option<GameFlow> {
-    match key {
-        Key::Left | Key::A if playing_state.snake.direction() != Direction::Right => {
-            playing_state.snake.set_next_direction(Direction::Left)
-        }
-        Key::Right | Key::D if playing_state.snake.direction() != Direction::Left => {
-            playing_state.snake.set_next_direction(Direction::Right)
-        }
-        Key::Up | Key::W if playing_state.snake.direction() != Direction::Down => {
-            playing_state.snake.set_next_direction(Direction::Up)
-        }
-        Key::Down | Key::S if playing_state.snake.direction() != Direction::Up => {
-            playing_state.snake.set_next_direction(Direction::Down)
+    for _ in 0..playing_state.timestep.update(clock) {
+        if let Some(game_flow) = tick_playing(playing_state, events) {
+            return Some(game_flow);
         }
-        _ => (),
     }
 
     None
 }
 
-fn handle_key_press_game_over(key: &Key) -> Option<GameFlow> {
-    match key {
-        Key::Space => Some(GameFlow::StartNew),
-        _ => None,
+/// Moves or grows the snake by one cell.
+fn tick_playing(playing_state: &mut PlayingState, events: &mut EventQueue) -> Option<GameFlow> {
+    playing_state.ticks += 1;
+    playing_state.power_ups.tick();
+    playing_state
+        .snake
+        .set_ghost(playing_state.power_ups.is_active(PowerUp::Ghost));
+    if let Some(grow_every) = playing_state.rules.grow_every {
+        if playing_state.ticks.is_multiple_of(u64::from(grow_every)) {
+            playing_state.snake.grow();
+        }
     }
-}
 
-impl Game {
-    pub fn new(settings: GameSettings) -> Self {
+    let direction = playing_state.snake.direction();
+    match playing_state.snake.advance(&playing_state.pickups) {
+        Ok(cell) => {
+            if playing_state.snake.direction() != direction {
+                events.push(GameEvent::Turned {
+                    position: playing_state.snake.head(),
+                });
+            }
+            events.push(GameEvent::Moved {
+                position: playing_state.snake.head(),
+                length: playing_state.snake.length(),
+            });
+
+            if let Some(grow_every) = playing_state.rules.grow_every {
+                survive_move(playing_state, grow_every, events);
+            }
+
+            let eaten = match cell {
+                NewCell::Pickup(index) => Some((index, playing_state.pickups.remove(index))),
+                NewCell::Empty => None,
+            };
+            if let Some((_, eaten)) = eaten {
+                match eaten.pickup_kind {
+                    PickupKind::Cherry | PickupKind::Apple => {
+                        eat_cherry(playing_state, &eaten, events)
+                    }
+                    PickupKind::PowerUp(power_up) => {
+                        if power_up == PowerUp::Shrink {
+                            playing_state.snake.shrink(SHRINK_SEGMENTS);
+                        }
+                        playing_state.power_ups.activate(power_up);
+                        events.push(GameEvent::PoweredUp {
+                            position: eaten.position,
+                            power_up,
+                        });
+                    }
+                    PickupKind::Poison => {
+                        playing_state.snake.shrink(POISON_SEGMENTS);
+                        let points = POISON_POINTS.min(playing_state.score);
+                        playing_state.score -= points;
+                        events.push(GameEvent::Poisoned {
+                            position: eaten.position,
+                            points,
+                        });
+                    }
+                    PickupKind::Bomb | PickupKind::Spikes(_) => {
+                        return Some(die(
+                            playing_state,
+                            Collision::Hazard(eaten.position),
+                            events,
+                        ));
+                    }
+                }
+            }
+
+            // lethal hazards on the last free cells leave nothing the snake could still go onto,
+            // poison can still be eaten
+            let lethal_hazards = playing_state
+                .pickups
+                .iter()
+                .filter(|pickup| pickup.pickup_kind.is_lethal())
+                .count();
+            if playing_state.snake.occupancy().free_count() as usize <= lethal_hazards {
+                playing_state.score += victory_bonus(field_size(playing_state.game_size));
+                events.push(GameEvent::Won {
+                    position: playing_state.snake.head(),
+                });
+                return Some(GameFlow::Win);
+            }
+
+            if let Some((index, eaten)) = eaten {
+                // only a cherry is recreated in its place, unless the others take the cells left
+                if eaten.pickup_kind == PickupKind::Cherry {
+                    replace_cherry(playing_state, index, events);
+                    spawn_power_up(playing_state, events);
+                    spawn_hazard(playing_state, events);
+                }
+            }
+
+            age_pickups(playing_state, events);
+            wander_pickups(playing_state);
+            top_up_cherries(playing_state, events);
+
+            if let Some(collision) = roll_spikes(playing_state) {
+                return Some(die(playing_state, collision, events));
+            }
+        }
+        Err(collision) => return Some(die(playing_state, collision, events)),
+    }
+
+    None
+}
+
+/// Smashes the head, the snake crumbles before the game is over.
+fn die(playing_state: &PlayingState, collision: Collision, events: &mut EventQueue) -> GameFlow {
+    events.push(GameEvent::Died {
+        position: playing_state.snake.head(),
+        cause: collision,
+    });
+    GameFlow::Die(collision)
+}
+
+fn eat_cherry(playing_state: &mut PlayingState, eaten: &Pickup, events: &mut EventQueue) {
+    let points = match playing_state.power_ups.is_active(PowerUp::DoublePoints) {
+        true => eaten.points() * 2,
+        false => eaten.points(),
+    };
+    playing_state.score += points;
+    playing_state.cherries_eaten += 1;
+    events.push(GameEvent::AteFood {
+        position: eaten.position,
+        points,
+    });
+
+    raise_level(playing_state, level_for_score(playing_state.score), events);
+}
+
+/// Scores the move, the level goes up with the parts the snake grew by.
+fn survive_move(playing_state: &mut PlayingState, grow_every: u32, events: &mut EventQueue) {
+    playing_state.score = u32::try_from(playing_state.ticks).unwrap_or(u32::MAX);
+
+    let grown = playing_state.ticks / u64::from(grow_every);
+    raise_level(
+        playing_state,
+        level_for_score(u32::try_from(grown).unwrap_or(u32::MAX)),
+        events,
+    );
+}
+
+fn raise_level(playing_state: &mut PlayingState, level: u32, events: &mut EventQueue) {
+    if level > playing_state.level {
+        playing_state.level = level;
+        events.push(GameEvent::LevelUp {
+            position: playing_state.snake.head(),
+            level,
+        });
+    }
+}
+
+/// Puts a random power-up on the field by chance, unless there is one already.
+fn spawn_power_up(playing_state: &mut PlayingState, events: &mut EventQueue) {
+    let on_field = playing_state
+        .pickups
+        .iter()
+        .any(|pickup| matches!(pickup.pickup_kind, PickupKind::PowerUp(_)));
+    if on_field
+        || !playing_state
+            .rng
+            .gen_bool(playing_state.rules.power_up_chance)
+    {
+        return;
+    }
+
+    let power_up = POWER_UPS[playing_state.rng.gen_range(0, POWER_UPS.len())];
+    spawn_pickup(playing_state, PickupKind::PowerUp(power_up), events);
+}
+
+/// Puts poison, a bomb or spikes on the field by chance, unless there are enough hazards.
+fn spawn_hazard(playing_state: &mut PlayingState, events: &mut EventQueue) {
+    let on_field = playing_state
+        .pickups
+        .iter()
+        .filter(|pickup| pickup.pickup_kind.is_hazard())
+        .count();
+    if on_field >= MAX_HAZARDS
+        || !playing_state
+            .rng
+            .gen_bool(playing_state.rules.hazard_chance)
+    {
+        return;
+    }
+
+    let hazard = match playing_state.rng.gen_range(0, 3) {
+        0 => PickupKind::Poison,
+        1 => PickupKind::Bomb,
+        _ => {
+            let directions = [
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+                Direction::Up,
+            ];
+            PickupKind::Spikes(directions[playing_state.rng.gen_range(0, directions.len())])
+        }
+    };
+    spawn_pickup(playing_state, hazard, events);
+}
+
+/// Puts a new cherry in place of the one at the index, unless the other pickups take the cells
+/// left. Returns whether it did.
+fn replace_cherry(playing_state: &mut PlayingState, index: usize, events: &mut EventQueue) -> bool {
+    match new_cherry_pickup(
+        &playing_state.snake,
+        &playing_state.pickups,
+        &playing_state.rules,
+        &mut playing_state.rng,
+    ) {
+        Some(pickup) => {
+            events.push(GameEvent::PickupSpawned {
+                position: pickup.position,
+            });
+            playing_state.pickups.insert(index, pickup);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Puts cherries on the field until there are as many as the rules ask for, when cells were
+/// missing for them before.
+fn top_up_cherries(playing_state: &mut PlayingState, events: &mut EventQueue) {
+    let cherries = playing_state
+        .pickups
+        .iter()
+        .filter(|pickup| pickup.pickup_kind == PickupKind::Cherry)
+        .count() as u32;
+
+    for _ in cherries..playing_state.rules.food_count {
+        match new_cherry_pickup(
+            &playing_state.snake,
+            &playing_state.pickups,
+            &playing_state.rules,
+            &mut playing_state.rng,
+        ) {
+            Some(pickup) => {
+                events.push(GameEvent::PickupSpawned {
+                    position: pickup.position,
+                });
+                playing_state.pickups.push(pickup);
+            }
+            None => return,
+        }
+    }
+}
+
+/// Power-ups and hazards vanish after a while.
+fn spawn_pickup(
+    playing_state: &mut PlayingState,
+    pickup_kind: PickupKind,
+    events: &mut EventQueue,
+) {
+    let lifetime = match pickup_kind {
+        PickupKind::Cherry | PickupKind::Apple => None,
+        PickupKind::PowerUp(_) => Some(POWER_UP_LIFETIME),
+        _ => Some(HAZARD_LIFETIME),
+    };
+    if let Some(pickup) = Pickup::new_random(
+        pickup_kind,
+        playing_state.snake.occupancy(),
+        &playing_state.pickups,
+        &mut playing_state.rng,
+    ) {
+        events.push(GameEvent::PickupSpawned {
+            position: pickup.position,
+        });
+        playing_state.pickups.push(pickup.with_lifetime(lifetime));
+    }
+}
+
+/// Counts a move on every pickup, the ones which outlived their lifetime vanish and the cherries
+/// among them come up again elsewhere.
+fn age_pickups(playing_state: &mut PlayingState, events: &mut EventQueue) {
+    let mut index = 0;
+    while index < playing_state.pickups.len() {
+        if !playing_state.pickups[index].grow_older() {
+            index += 1;
+            continue;
+        }
+
+        let expired = playing_state.pickups.remove(index);
+        events.push(GameEvent::PickupExpired {
+            position: expired.position,
+        });
+        // the new cherry takes the place of the expired one, the next pickup moved up otherwise
+        if expired.pickup_kind == PickupKind::Cherry && replace_cherry(playing_state, index, events)
+        {
+            index += 1;
+        }
+    }
+}
+
+/// Steps every wandering pickup to a random neighbouring cell free of the snake and the other
+/// pickups, the ones closed in stay.
+fn wander_pickups(playing_state: &mut PlayingState) {
+    for index in 0..playing_state.pickups.len() {
+        if !playing_state.pickups[index].wanders {
+            continue;
+        }
+
+        let position = playing_state.pickups[index].position;
+        let free: Vec<Point> = [
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+            Direction::Up,
+        ]
+        .iter()
+        .map(|direction| position.offset(direction.offset()))
+        .filter(|next| {
+            !playing_state.snake.occupancy().is_occupied(*next)
+                && playing_state
+                    .pickups
+                    .iter()
+                    .all(|pickup| pickup.position != *next)
+        })
+        .collect();
+        if !free.is_empty() {
+            let next = free[playing_state.rng.gen_range(0, free.len())];
+            playing_state.pickups[index].position = next;
+        }
+    }
+}
+
+/// Rolls the spikes on by a cell, they turn back when something is in the way and stay when
+/// they are stuck.
+///
+/// Returns the collision when spikes roll into the head of the snake.
+fn roll_spikes(playing_state: &mut PlayingState) -> Option<Collision> {
+    if !playing_state.ticks.is_multiple_of(SPIKES_MOVE_EVERY) {
+        return None;
+    }
+
+    let head = playing_state.snake.head();
+    for index in 0..playing_state.pickups.len() {
+        let position = playing_state.pickups[index].position;
+        let direction = match playing_state.pickups[index].pickup_kind {
+            PickupKind::Spikes(direction) => direction,
+            _ => continue,
+        };
+
+        for &heading in &[direction, direction.opposite()] {
+            let next = position.offset(heading.offset());
+            if next == head {
+                return Some(Collision::Hazard(next));
+            }
+
+            let blocked = playing_state.snake.occupancy().is_occupied(next)
+                || playing_state
+                    .pickups
+                    .iter()
+                    .any(|pickup| pickup.position == next);
+            if !blocked {
+                playing_state.pickups[index] = Pickup {
+                    pickup_kind: PickupKind::Spikes(heading),
+                    position: next,
+                    ..playing_state.pickups[index]
+                };
+                break;
+            }
+        }
+    }
+
+    None
+}
+
+fn handle_key_press_playing(playing_state: &mut PlayingState, key: &Key) -> Option<GameFlow> {
+    match key {
+        Key::Left | Key::A if playing_state.snake.direction() != Direction::Right => {
+            playing_state.snake.set_next_direction(Direction::Left)
+        }
+        Key::Right | Key::D if playing_state.snake.direction() != Direction::Left => {
+            playing_state.snake.set_next_direction(Direction::Right)
+        }
+        Key::Up | Key::W if playing_state.snake.direction() != Direction::Down => {
+            playing_state.snake.set_next_direction(Direction::Up)
+        }
+        Key::Down | Key::S if playing_state.snake.direction() != Direction::Up => {
+            playing_state.snake.set_next_direction(Direction::Down)
+        }
+        _ => (),
+    }
+
+    None
+}
+
+fn update_dying(dying_state: &DyingState, now: Duration) -> Option<GameFlow> {
+    if dying_state.elapsed(now) >= DEATH_DURATION {
+        return Some(GameFlow::ShowGameOver(dying_state.summary.clone()));
+    }
+
+    None
+}
+
+fn handle_key_press_dying(dying_state: &DyingState, key: &Key) -> Option<GameFlow> {
+    // skips the rest of the animation
+    match key {
+        Key::Space => Some(GameFlow::ShowGameOver(dying_state.summary.clone())),
+        _ => None,
+    }
+}
+
+/// A new game is played in the mode of the settings.
+fn handle_key_press_menu(
+    menu_state: &mut MenuState,
+    key: &Key,
+    mode: GameMode,
+    audio: &mut Audio,
+) -> Option<GameFlow> {
+    match key {
+        Key::Up | Key::W => menu_state.move_selection(false),
+        Key::Down | Key::S => menu_state.move_selection(true),
+        Key::Return | Key::Space => {
+            return match menu_state.items[menu_state.selected] {
+                MenuItem::Continue => Some(GameFlow::Continue),
+                MenuItem::NewGame => Some(GameFlow::StartNew(GameSetup::new(mode, None))),
+                // the seed of the day is filled in when the game starts
+                MenuItem::Daily => Some(GameFlow::StartNew(GameSetup::new(GameMode::Daily, None))),
+                MenuItem::EnterSeed => Some(GameFlow::ShowSeedEntry),
+                MenuItem::Stats => Some(GameFlow::ShowStats),
+            };
+        }
+        _ => return None,
+    }
+
+    audio.play(Sound::MenuMove);
+    None
+}
+
+/// Letter or digit typed by the key, as it appears in seed codes.
+fn key_character(key: &Key) -> Option<char> {
+    u8::try_from(key.code())
+        .ok()
+        .map(|code| char::from(code).to_ascii_uppercase())
+        .filter(char::is_ascii_alphanumeric)
+}
+
+fn handle_key_press_seed_entry(
+    seed_entry_state: &mut SeedEntryState,
+    key: &Key,
+    mode: GameMode,
+) -> Option<GameFlow> {
+    match key {
+        Key::Escape => return Some(GameFlow::ShowMenu),
+        Key::Return => {
+            return normalize_seed_code(&seed_entry_state.code)
+                .map(|seed| GameFlow::StartNew(GameSetup::new(mode, Some(seed))));
+        }
+        Key::Backspace => {
+            seed_entry_state.code.pop();
+        }
+        _ => {
+            if let Some(character) = key_character(key) {
+                if seed_entry_state.code.len() < MAX_SEED_CODE_LENGTH {
+                    seed_entry_state.code.push(character);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn handle_key_press_stats(key: &Key) -> Option<GameFlow> {
+    match key {
+        Key::Escape | Key::Space | Key::Return => Some(GameFlow::ShowMenu),
+        _ => None,
+    }
+}
+
+fn handle_key_press_game_over(game_over_state: &GameOverState, key: &Key) -> Option<GameFlow> {
+    let summary = &game_over_state.summary;
+    match key {
+        // the same seed again, so that a challenge can be retried
+        Key::Space => Some(GameFlow::StartNew(GameSetup::new(
+            summary.mode,
+            summary.seed.clone(),
+        ))),
+        Key::Escape => Some(GameFlow::ShowMenu),
+        _ => None,
+    }
+}
+
+/// Reads a file of the player profile, starting over when it is missing or broken.
+fn read_profile_file<T, F>(profile: Option<&Storage>, name: &str, description: &str, parse: F) -> T
+where
+    T: Default,
+    F: Fn(&str) -> Result<T, SaveError>,
+{
+    match profile.map(|profile| profile.read(name)) {
+        Some(Ok(Some(text))) => parse(&text).unwrap_or_else(|error| {
+            eprintln!(
+                "Failed to read the {}, starting over: {}",
+                description, error
+            );
+            T::default()
+        }),
+        Some(Err(error)) => {
+            eprintln!("Failed to read the {}: {}", description, error);
+            T::default()
+        }
+        _ => T::default(),
+    }
+}
+
+impl Game {
+    pub fn new(settings: GameSettings, assets: AssetSource) -> Self {
         let sprite_renderer = SpriteRenderer::new(
             settings.opengl,
-            SpriteRendererSettings::new(
-                settings.game_size,
-                settings.sprite_size * settings.sprite_scale,
-            ),
+            SpriteRendererSettings::new(settings.game_size, settings.sprite_size),
+        );
+
+        let clock = Box::new(RealClock::new());
+        let storage = Storage::user_data();
+        let profile = storage.as_ref().map(|storage| {
+            storage
+                .subdirectory("profiles")
+                .subdirectory(&settings.profile)
+        });
+        let stats = read_profile_file(profile.as_ref(), STATS_FILE, "statistics", Stats::parse);
+        let achievements = read_profile_file(
+            profile.as_ref(),
+            ACHIEVEMENTS_FILE,
+            "achievements",
+            Achievements::parse,
         );
+        let can_continue = match &storage {
+            Some(storage) => storage.exists(SAVE_FILE),
+            None => false,
+        };
+        let sprites = SpriteData::load(&assets).expect("Failed to load the sprites.");
+        let asset_watcher = assets.watch(&[SNAKE_SHEET, FONT_SHEET, FONT_LAYOUT, SETTINGS]);
 
-        let game_size = settings.game_size;
-        let sprite_scale = settings.sprite_scale;
+        let mut audio = Audio::new(audio::default_backend(), settings.audio);
+        audio.start_music();
 
         Game {
             sprite_renderer,
             settings,
-            sprites: SpriteData::new(sprite_scale),
-            state: GameState::new_playing(game_size),
+            sprites,
+            state: GameState::new_menu(can_continue),
+            effects: Effects::new(),
+            events: EventQueue::new(),
+            audio,
+            clock,
+            storage,
+            profile,
+            stats,
+            achievements,
+            toasts: Toasts::new(),
+            assets,
+            asset_watcher,
         }
     }
 
@@ -294,54 +1741,1234 @@ impl Game {
             self.render(&render_args);
         }
 
-        if let Some(_) = event.update_args() {
-            self.update().map(|f| self.handle_game_flow(f));
+        if let Some(update_args) = event.update_args() {
+            self.reload_changed_assets();
+
+            if let Some(game_flow) = self.update(update_args.dt) {
+                self.handle_game_flow(game_flow);
+            }
         }
 
         if let Some(Button::Keyboard(key)) = event.press_args() {
-            self.handle_key_press(&key)
-                .map(|f| self.handle_game_flow(f));
+            if let Some(game_flow) = self.handle_key_press(&key) {
+                self.handle_game_flow(game_flow);
+            }
+        }
+    }
+
+    /// Rebuilds the sprites and settings when their files change in development mode.
+    ///
+    /// The running game keeps its board, a new board size is used starting from the next game.
+    fn reload_changed_assets(&mut self) {
+        let changed = match &mut self.asset_watcher {
+            Some(asset_watcher) => asset_watcher.poll(),
+            None => return,
+        };
+
+        if changed.is_empty() {
+            return;
+        }
+
+        if changed.iter().any(|name| name == SETTINGS) {
+            match GameSettings::load(
+                self.settings.opengl,
+                self.settings.sprite_size,
+                &self.assets,
+            ) {
+                Ok(settings) => {
+                    self.audio.set_settings(settings.audio);
+                    self.settings = settings;
+                }
+                Err(error) => eprintln!("Failed to reload the settings: {}", error),
+            }
+        }
+
+        if changed.iter().any(|name| name != SETTINGS) {
+            match SpriteData::load(&self.assets) {
+                Ok(sprites) => self.sprites = sprites,
+                Err(error) => eprintln!("Failed to reload the sprites: {}", error),
+            }
+        }
+
+        self.update_renderer_settings(self.current_game_size());
+    }
+
+    fn current_game_size(&self) -> Size {
+        match &self.state {
+            GameState::Menu(_) | GameState::SeedEntry(_) | GameState::Stats => {
+                self.settings.game_size
+            }
+            GameState::Playing(playing_state) => playing_state.game_size,
+            GameState::Dying(dying_state) => dying_state.playing_state.game_size,
+            GameState::GameOver(_) => self.settings.game_size,
         }
     }
 
+    fn update_renderer_settings(&mut self, game_size: Size) {
+        self.sprite_renderer
+            .set_settings(SpriteRendererSettings::new(
+                game_size,
+                self.settings.sprite_size,
+            ));
+    }
+
     fn handle_game_flow(&mut self, game_flow: GameFlow) {
         match game_flow {
-            GameFlow::StartNew => {
-                self.state = GameState::new_playing(self.settings.game_size);
+            GameFlow::StartNew(mut setup) => {
+                if let (GameMode::Daily, None) = (setup.mode, &setup.seed) {
+                    setup.seed = Some(today_seed_code());
+                }
+
+                let game_size = self.settings.game_size_for(&setup);
+                let rules = self.settings.rules_for(&setup);
+                let tail_rule = self.settings.tail_rule_for(&setup);
+
+                self.update_renderer_settings(game_size);
+                self.effects.clear();
+                self.audio.play(Sound::MenuSelect);
+                self.audio.start_music();
+                self.state = GameState::new_playing(
+                    game_size,
+                    setup,
+                    self.settings.difficulty,
+                    rules,
+                    self.settings.tick_duration,
+                    self.clock.as_ref(),
+                );
+                if let GameState::Playing(playing_state) = &mut self.state {
+                    playing_state.snake.set_tail_rule(tail_rule);
+                    self.achievements.start_game(GameProgress::new(
+                        playing_state.difficulty,
+                        field_size(playing_state.game_size),
+                        playing_state.snake.length(),
+                    ));
+                }
+            }
+            GameFlow::Continue => match self.load_game() {
+                Ok(playing_state) => {
+                    self.update_renderer_settings(playing_state.game_size);
+                    self.effects.clear();
+                    self.audio.play(Sound::MenuSelect);
+                    self.audio.start_music();
+                    self.achievements.start_game(GameProgress::continued(
+                        playing_state.difficulty,
+                        field_size(playing_state.game_size),
+                        playing_state.snake.length(),
+                        playing_state.cherries_eaten,
+                    ));
+                    self.state = GameState::Playing(playing_state);
+                }
+                Err(error) => {
+                    eprintln!("Failed to continue the saved game: {}", error);
+                    self.handle_game_flow(GameFlow::StartNew(GameSetup::new(
+                        self.settings.mode,
+                        None,
+                    )));
+                }
+            },
+            GameFlow::Die(collision) => {
+                self.finish_game();
+                // the playing state moves into the dying one, the menu only fills in
+                if let GameState::Playing(playing_state) =
+                    mem::replace(&mut self.state, GameState::new_menu(false))
+                {
+                    self.state =
+                        GameState::new_dying(playing_state, collision, self.clock.as_ref());
+                }
+            }
+            GameFlow::Win => self.finish_without_crash(GameEnd::BoardCleared),
+            GameFlow::TimeUp => self.finish_without_crash(GameEnd::TimeUp),
+            GameFlow::ShowGameOver(summary) => {
+                let new_best = self.stats.record(&summary);
+                self.save_stats();
+                self.state = GameState::new_game_over(summary, new_best);
+            }
+            GameFlow::ShowMenu => {
+                let can_continue = match &self.storage {
+                    Some(storage) => storage.exists(SAVE_FILE),
+                    None => false,
+                };
+                self.update_renderer_settings(self.settings.game_size);
+                self.state = GameState::new_menu(can_continue);
             }
-            GameFlow::ShowGameOver(score) => {
-                self.state = GameState::new_game_over(score);
+            GameFlow::ShowSeedEntry => {
+                self.state = GameState::SeedEntry(SeedEntryState {
+                    code: String::new(),
+                });
+            }
+            GameFlow::ShowStats => {
+                self.state = GameState::Stats;
+            }
+        }
+    }
+
+    /// Goes straight to the game over screen, there is no crash to show.
+    fn finish_without_crash(&mut self, end: GameEnd) {
+        self.finish_game();
+        let summary = match &self.state {
+            GameState::Playing(playing_state) => playing_state.summary(end, self.clock.now()),
+            _ => return,
+        };
+        self.handle_game_flow(GameFlow::ShowGameOver(summary));
+    }
+
+    /// Stops everything which only goes on while playing.
+    fn finish_game(&mut self) {
+        self.audio.stop_music();
+        self.achievements.end_game();
+        // a finished game can't be continued
+        if let Some(Err(error)) = self
+            .storage
+            .as_ref()
+            .map(|storage| storage.remove(SAVE_FILE))
+        {
+            eprintln!("Failed to remove the saved game: {}", error);
+        }
+    }
+
+    /// Saves the game in progress, so that it can be continued after the next start.
+    pub fn quit(&mut self) {
+        self.save_game();
+    }
+
+    fn save_game(&mut self) -> bool {
+        let (playing_state, storage) = match (&self.state, &self.storage) {
+            (GameState::Playing(playing_state), Some(storage)) => (playing_state, storage),
+            _ => return false,
+        };
+
+        let snapshot = playing_state.snapshot(self.clock.now());
+        match storage.write(SAVE_FILE, &snapshot.to_text()) {
+            Ok(()) => true,
+            Err(error) => {
+                eprintln!("Failed to save the game: {}", error);
+                false
             }
         }
     }
 
+    fn save_achievements(&self) {
+        if let Some(profile) = &self.profile {
+            if let Err(error) = profile.write(ACHIEVEMENTS_FILE, &self.achievements.to_text()) {
+                eprintln!("Failed to save the achievements: {}", error);
+            }
+        }
+    }
+
+    fn save_stats(&self) {
+        if let Some(profile) = &self.profile {
+            if let Err(error) = profile.write(STATS_FILE, &self.stats.to_text()) {
+                eprintln!("Failed to save the statistics: {}", error);
+            }
+        }
+    }
+
+    fn load_game(&self) -> Result<PlayingState, String> {
+        let storage = self.storage.as_ref().ok_or("no data directory")?;
+        let text = storage
+            .read(SAVE_FILE)
+            .map_err(|error| error.to_string())?
+            .ok_or("no saved game")?;
+        let snapshot = Snapshot::parse(&text).map_err(|error: SaveError| error.to_string())?;
+
+        Ok(PlayingState::restore(
+            snapshot,
+            self.settings.tick_duration,
+            self.clock.as_ref(),
+        ))
+    }
+
     fn render(&mut self, args: &RenderArgs) {
         match &self.state {
+            GameState::Menu(menu_state) => {
+                render_menu(&self.sprites, &mut self.sprite_renderer, menu_state, args);
+            }
+            GameState::SeedEntry(seed_entry_state) => {
+                render_seed_entry(
+                    &self.sprites,
+                    &mut self.sprite_renderer,
+                    seed_entry_state,
+                    args,
+                );
+            }
+            GameState::Stats => {
+                render_stats(
+                    &self.sprites,
+                    &mut self.sprite_renderer,
+                    &self.stats,
+                    &self.achievements,
+                    args,
+                );
+            }
             GameState::Playing(playing_state) => {
                 render_playing(
                     &self.sprites,
                     &mut self.sprite_renderer,
-                    &playing_state,
+                    playing_state,
+                    None,
+                    &self.effects,
+                    self.clock.now(),
                     args,
                 );
             }
-            GameState::GameOver(score) => {
-                render_game_over(&self.sprites, &mut self.sprite_renderer, *score, args);
+            GameState::Dying(dying_state) => {
+                render_playing(
+                    &self.sprites,
+                    &mut self.sprite_renderer,
+                    &dying_state.playing_state,
+                    Some(dying_state),
+                    &self.effects,
+                    self.clock.now(),
+                    args,
+                );
             }
+            GameState::GameOver(game_over_state) => {
+                render_game_over(
+                    &self.sprites,
+                    &mut self.sprite_renderer,
+                    game_over_state,
+                    args,
+                );
+            }
+        }
+
+        if !self.toasts.is_empty() {
+            render_toasts(&self.sprites, &mut self.sprite_renderer, &self.toasts, args);
         }
     }
 
-    fn update(&mut self) -> Option<GameFlow> {
-        match &mut self.state {
-            GameState::Playing(playing_state) => update_playing(playing_state),
-            _ => None,
+    fn update(&mut self, dt: f64) -> Option<GameFlow> {
+        let game_flow = match &mut self.state {
+            GameState::Playing(playing_state) => {
+                let game_flow = update_playing(
+                    playing_state,
+                    self.settings.tick_duration,
+                    self.clock.as_ref(),
+                    &mut self.events,
+                );
+                self.achievements
+                    .update_play_time(playing_state.play_time(self.clock.now()));
+                game_flow
+            }
+            GameState::Dying(dying_state) => update_dying(dying_state, self.clock.now()),
+            GameState::Menu(_)
+            | GameState::SeedEntry(_)
+            | GameState::Stats
+            | GameState::GameOver(_) => None,
+        };
+
+        self.events
+            .dispatch(&mut [&mut self.effects, &mut self.audio, &mut self.achievements]);
+        self.effects.update(dt);
+
+        let announcements = self.achievements.take_announcements();
+        for achievement in &announcements {
+            self.toasts
+                .show(format!("Unlocked {}", achievement.title), palette::YELLOW);
         }
+        if !announcements.is_empty() {
+            self.save_achievements();
+        }
+        self.toasts.update(dt);
+
+        game_flow
     }
 
     fn handle_key_press(&mut self, key: &Key) -> Option<GameFlow> {
+        if let (Key::M, false) = (key, self.state.takes_text()) {
+            self.audio.toggle_mute();
+            return None;
+        }
+
+        if let (Key::F5, GameState::Playing(playing_state)) = (key, &self.state) {
+            let head = playing_state.snake.head();
+            if self.save_game() {
+                self.effects
+                    .float_text("saved".to_string(), head, palette::WHITE);
+            }
+            return None;
+        }
+
         match &mut self.state {
+            GameState::Menu(menu_state) => {
+                handle_key_press_menu(menu_state, key, self.settings.mode, &mut self.audio)
+            }
+            GameState::SeedEntry(seed_entry_state) => {
+                handle_key_press_seed_entry(seed_entry_state, key, self.settings.mode)
+            }
+            GameState::Stats => handle_key_press_stats(key),
             GameState::Playing(playing_state) => handle_key_press_playing(playing_state, key),
-            GameState::GameOver(_) => handle_key_press_game_over(key),
+            GameState::Dying(dying_state) => handle_key_press_dying(dying_state, key),
+            GameState::GameOver(game_over_state) => {
+                handle_key_press_game_over(game_over_state, key)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::audio::recording::RecordingAudio;
+    use crate::audio::NullAudio;
+    use crate::clock::manual::ManualClock;
+    use pickup::PickupKind;
+
+    const TICK: Duration = Duration::from_millis(300);
+
+    /// Rules without portals or the chance of power-ups or hazards, which would change the course of the
+    /// games.
+    fn plain_rules() -> GameRules {
+        GameRules {
+            power_up_chance: 0.0,
+            hazard_chance: 0.0,
+            portal_pairs: 0,
+            ..GameRules::default()
+        }
+    }
+
+    fn new_playing_state(clock: &ManualClock) -> PlayingState {
+        PlayingState::new(
+            (12, 8),
+            GameSetup::new(GameMode::Classic, None),
+            Difficulty::Normal,
+            plain_rules(),
+            TICK,
+            clock,
+            GameRng::from_state(7),
+        )
+    }
+
+    /// Lets a tick pass, so that the snake moves once, and plays the sounds of what happened.
+    fn step(
+        playing_state: &mut PlayingState,
+        clock: &ManualClock,
+        audio: &mut Audio,
+    ) -> Option<GameFlow> {
+        clock.advance(level_tick_duration(TICK, playing_state.level));
+
+        let mut events = EventQueue::new();
+        let game_flow = update_playing(playing_state, TICK, clock, &mut events);
+        events.dispatch(&mut [audio]);
+        game_flow
+    }
+
+    fn head_x_after(elapsed: Duration, updates: u32) -> i32 {
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+        let mut events = EventQueue::new();
+
+        for _ in 0..updates {
+            clock.advance(elapsed / updates);
+            update_playing(&mut playing_state, TICK, &clock, &mut events);
+        }
+
+        playing_state.snake.head().x
+    }
+
+    #[test]
+    fn test_moves_for_elapsed_time() {
+        // the snake starts at (4, 0) heading right
+        assert_eq!(head_x_after(Duration::from_millis(299), 1), 4);
+        assert_eq!(head_x_after(Duration::from_millis(900), 1), 7);
+        // frame rate doesn't matter
+        assert_eq!(head_x_after(Duration::from_millis(900), 60), 7);
+        assert_eq!(head_x_after(Duration::from_millis(900), 4), 7);
+        // a stall only catches up a few moves
+        assert_eq!(head_x_after(TICK * 10, 1), 4 + MAX_CATCH_UP_TICKS as i32);
+    }
+
+    #[test]
+    fn test_difficulty_speed() {
+        let clock = ManualClock::default();
+        let mut playing_state = PlayingState::new(
+            (12, 8),
+            GameSetup::new(GameMode::Classic, None),
+            Difficulty::Hard,
+            plain_rules(),
+            TICK,
+            &clock,
+            GameRng::from_state(7),
+        );
+        let mut events = EventQueue::new();
+
+        // three moves in the time of two on normal
+        clock.advance(TICK * 2);
+        update_playing(&mut playing_state, TICK, &clock, &mut events);
+        assert_eq!(playing_state.ticks, 3);
+    }
+
+    #[test]
+    fn test_several_pickups() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = PlayingState::new(
+            (12, 8),
+            GameSetup::new(GameMode::Classic, None),
+            Difficulty::Normal,
+            GameRules {
+                food_count: 3,
+                ..plain_rules()
+            },
+            TICK,
+            &clock,
+            GameRng::from_state(7),
+        );
+
+        let positions = |playing_state: &PlayingState| -> Vec<Point> {
+            playing_state
+                .pickups
+                .iter()
+                .map(|pickup| pickup.position)
+                .collect()
+        };
+        let before = positions(&playing_state);
+        assert_eq!(before.len(), 3);
+        for (index, position) in before.iter().enumerate() {
+            assert!(!playing_state.snake.occupancy().is_occupied(*position));
+            assert!(!before[..index].contains(position));
+        }
+
+        // the snake starts at (4, 0) heading right, only the eaten pickup is replaced
+        playing_state.pickups[1].position = (5, 0).into();
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.cherries_eaten, 1);
+        let after = positions(&playing_state);
+        assert_eq!(after.len(), 3);
+        assert_eq!((after[0], after[2]), (before[0], before[2]));
+        assert!(!after.contains(&(5, 0).into()));
+    }
+
+    #[test]
+    fn test_portals() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = PlayingState::new(
+            (12, 8),
+            GameSetup::new(GameMode::Classic, None),
+            Difficulty::Normal,
+            GameRules {
+                food_count: MAX_FOOD_COUNT,
+                portal_pairs: MAX_PORTAL_PAIRS,
+                ..plain_rules()
+            },
+            TICK,
+            &clock,
+            GameRng::from_state(7),
+        );
+
+        let portals = playing_state.snake.portals().to_vec();
+        assert_eq!(portals.len(), MAX_PORTAL_PAIRS as usize);
+        for pickup in &playing_state.pickups {
+            assert!(portals
+                .iter()
+                .all(|portal| portal.other_end(pickup.position).is_none()));
+        }
+
+        // the snake starts at (4, 0) heading right
+        playing_state.pickups = vec![Pickup::new(PickupKind::Cherry, (0, 4))];
+        assert!(playing_state
+            .snake
+            .set_portals(vec![PortalPair::new((5, 0), (3, 3))]));
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.snake.head(), (4, 3).into());
+    }
+
+    #[test]
+    fn test_power_up_comes_up() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+        playing_state.rules.power_up_chance = 1.0;
+
+        // the snake starts at (4, 0) heading right
+        playing_state.pickups[0].position = (5, 0).into();
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        let kinds: Vec<PickupKind> = playing_state
+            .pickups
+            .iter()
+            .map(|pickup| pickup.pickup_kind)
+            .collect();
+        match kinds.as_slice() {
+            [PickupKind::Cherry, PickupKind::PowerUp(_)] => (),
+            _ => panic!("Expected a cherry and a power-up, got {:?}", kinds),
+        }
+
+        // only one power-up at a time
+        let cherry = playing_state.snake.head().offset((1, 0));
+        playing_state.pickups[0].position = cherry;
+        playing_state.pickups[1].position = (0, 5).into();
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.pickups.len(), 2);
+    }
+
+    #[test]
+    fn test_power_up_effects() {
+        let recording = RecordingAudio::default();
+        let mut audio = Audio::new(Box::new(recording.clone()), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+        let power_up_ahead = |playing_state: &mut PlayingState, power_up| {
+            let position = playing_state.snake.head().offset((1, 0));
+            playing_state.pickups = vec![Pickup::new(PickupKind::PowerUp(power_up), position)];
+        };
+
+        // the power-up is eaten without a new one, only the missing cherry comes up, the snake
+        // grows and then shrinks
+        power_up_ahead(&mut playing_state, PowerUp::Shrink);
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.pickups.len(), 1);
+        assert_eq!(playing_state.pickups[0].pickup_kind, PickupKind::Cherry);
+        assert_eq!(playing_state.snake.length(), 6 - SHRINK_SEGMENTS);
+        assert_eq!(playing_state.cherries_eaten, 0);
+        assert_eq!(recording.sounds(), vec![Sound::PowerUp]);
+
+        power_up_ahead(&mut playing_state, PowerUp::DoublePoints);
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        playing_state.pickups = vec![Pickup::new(
+            PickupKind::Cherry,
+            playing_state.snake.head().offset((1, 0)),
+        )];
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.score, 2);
+
+        // half the moves in the same time
+        power_up_ahead(&mut playing_state, PowerUp::SlowMotion);
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        let ticks = playing_state.ticks;
+        let mut events = EventQueue::new();
+        clock.advance(TICK * 2);
+        update_playing(&mut playing_state, TICK, &clock, &mut events);
+        assert_eq!(playing_state.ticks, ticks + 1);
+    }
+
+    fn put_pickup(playing_state: &mut PlayingState, pickup_kind: PickupKind, position: (i32, i32)) {
+        playing_state.pickups = vec![Pickup::new(pickup_kind, position)];
+    }
+
+    #[test]
+    fn test_poison() {
+        let recording = RecordingAudio::default();
+        let mut audio = Audio::new(Box::new(recording.clone()), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+        playing_state.score = 1;
+
+        // the snake starts at (4, 0) heading right, the poison isn't replaced but the missing
+        // cherry comes up
+        put_pickup(&mut playing_state, PickupKind::Poison, (5, 0));
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.pickups.len(), 1);
+        assert_eq!(playing_state.pickups[0].pickup_kind, PickupKind::Cherry);
+        assert_eq!(playing_state.snake.length(), 6 - POISON_SEGMENTS);
+        assert_eq!(playing_state.score, 0);
+        assert_eq!(recording.sounds(), vec![Sound::Poison]);
+    }
+
+    #[test]
+    fn test_bomb() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+
+        put_pickup(&mut playing_state, PickupKind::Bomb, (5, 0));
+        match step(&mut playing_state, &clock, &mut audio) {
+            Some(GameFlow::Die(collision)) => {
+                assert_eq!(collision, Collision::Hazard((5, 0).into()))
+            }
+            _ => panic!("Expected the bomb to end the game"),
+        }
+    }
+
+    #[test]
+    fn test_spikes() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+
+        // they turn back at the border, on every other move
+        let mut playing_state = new_playing_state(&clock);
+        put_pickup(
+            &mut playing_state,
+            PickupKind::Spikes(Direction::Left),
+            (0, 3),
+        );
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.pickups[0].position, (0, 3).into());
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(
+            (
+                playing_state.pickups[0].pickup_kind,
+                playing_state.pickups[0].position
+            ),
+            (PickupKind::Spikes(Direction::Right), (1, 3).into())
+        );
+
+        // and roll into the head
+        let mut playing_state = new_playing_state(&clock);
+        put_pickup(
+            &mut playing_state,
+            PickupKind::Spikes(Direction::Left),
+            (7, 0),
+        );
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        match step(&mut playing_state, &clock, &mut audio) {
+            Some(GameFlow::Die(collision)) => {
+                assert_eq!(collision, Collision::Hazard((6, 0).into()))
+            }
+            _ => panic!("Expected the spikes to end the game"),
+        }
+    }
+
+    #[test]
+    fn test_hazards_come_up() {
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+        let mut events = EventQueue::new();
+
+        spawn_hazard(&mut playing_state, &mut events);
+        assert_eq!(playing_state.pickups.len(), 1);
+
+        playing_state.rules.hazard_chance = 1.0;
+        for _ in 0..MAX_HAZARDS + 2 {
+            spawn_hazard(&mut playing_state, &mut events);
+        }
+        let hazards = playing_state
+            .pickups
+            .iter()
+            .filter(|pickup| pickup.pickup_kind.is_hazard())
+            .count();
+        assert_eq!(hazards, MAX_HAZARDS);
+        assert_eq!(playing_state.pickups.len(), 1 + MAX_HAZARDS);
+    }
+
+    #[test]
+    fn test_expiring_pickups() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+
+        // the power-up just vanishes, the cherry comes up again elsewhere
+        playing_state.pickups = vec![
+            Pickup::new(PickupKind::Cherry, (0, 4)).with_lifetime(Some(2)),
+            Pickup::new(PickupKind::PowerUp(PowerUp::Ghost), (0, 3)).with_lifetime(Some(1)),
+        ];
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.pickups.len(), 1);
+        assert_eq!(playing_state.pickups[0].age, 1);
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.pickups.len(), 1);
+        assert_eq!(playing_state.pickups[0].pickup_kind, PickupKind::Cherry);
+        assert_eq!(playing_state.pickups[0].lifetime, None);
+
+        // the older the cherry, the fewer the points
+        playing_state.pickups = vec![Pickup {
+            age: 5,
+            ..Pickup::new(
+                PickupKind::Cherry,
+                playing_state.snake.head().offset((1, 0)),
+            )
+            .with_lifetime(Some(10))
+        }];
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.score, 2);
+    }
+
+    #[test]
+    fn test_expiring_next_to_each_other() {
+        let clock = ManualClock::default();
+        // two rows of six cells, the pickups hold every cell the snake leaves free
+        let mut playing_state = new_tiny_playing_state((8, 5), &clock);
+        playing_state.pickups = vec![
+            Pickup::new(PickupKind::Cherry, (0, 1)).with_lifetime(Some(1)),
+            Pickup::new(PickupKind::PowerUp(PowerUp::Ghost), (1, 1)).with_lifetime(Some(1)),
+            Pickup::new(PickupKind::Cherry, (2, 1)).with_lifetime(Some(1)),
+            Pickup::new(PickupKind::Bomb, (3, 1)).with_lifetime(Some(1)),
+        ];
+        for &position in &[(4, 1), (5, 1), (5, 0)] {
+            playing_state
+                .pickups
+                .push(Pickup::new(PickupKind::Poison, position));
+        }
+
+        let mut events = EventQueue::new();
+        age_pickups(&mut playing_state, &mut events);
+        let kinds: Vec<PickupKind> = playing_state
+            .pickups
+            .iter()
+            .map(|pickup| pickup.pickup_kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                PickupKind::Cherry,
+                PickupKind::Cherry,
+                PickupKind::Poison,
+                PickupKind::Poison,
+                PickupKind::Poison
+            ]
+        );
+        // the new cherries only come up on the cells the old ones left
+        assert!(playing_state.pickups[..2].iter().all(|pickup| {
+            [(0, 1).into(), (1, 1).into(), (2, 1).into(), (3, 1).into()].contains(&pickup.position)
+        }));
+    }
+
+    #[test]
+    fn test_wandering_cherries() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+
+        playing_state.pickups = vec![Pickup::new(PickupKind::Cherry, (0, 4)).wandering(true)];
+        for _ in 0..3 {
+            let before = playing_state.pickups[0].position;
+            assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+            let after = playing_state.pickups[0].position;
+            assert_eq!((before.x - after.x).abs() + (before.y - after.y).abs(), 1);
+            assert!(!playing_state.snake.occupancy().is_occupied(after));
+        }
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(level_for_score(0), 1);
+        assert_eq!(level_for_score(POINTS_PER_LEVEL - 1), 1);
+        assert_eq!(level_for_score(POINTS_PER_LEVEL), 2);
+
+        let tick_duration = Duration::from_millis(300);
+        assert_eq!(level_tick_duration(tick_duration, 1), tick_duration);
+        assert!(level_tick_duration(tick_duration, 2) < tick_duration);
+        assert_eq!(
+            level_tick_duration(tick_duration, MAX_SPEEDUP_LEVEL + 5),
+            level_tick_duration(tick_duration, MAX_SPEEDUP_LEVEL)
+        );
+    }
+
+    #[test]
+    fn test_time_attack() {
+        let clock = ManualClock::default();
+        let mut playing_state = PlayingState::new(
+            (12, 8),
+            GameSetup::new(GameMode::TimeAttack, None),
+            Difficulty::Normal,
+            plain_rules(),
+            TICK,
+            &clock,
+            GameRng::from_state(7),
+        );
+        let mut events = EventQueue::new();
+        let time_limit = playing_state
+            .rules
+            .time_limit
+            .expect("The time of a time attack is limited");
+
+        clock.advance(time_limit - Duration::from_secs(1));
+        assert!(update_playing(&mut playing_state, TICK, &clock, &mut events).is_none());
+        clock.advance(Duration::from_secs(1));
+        match update_playing(&mut playing_state, TICK, &clock, &mut events) {
+            Some(GameFlow::TimeUp) => (),
+            _ => panic!("Expected the time to run out"),
+        }
+
+        assert_eq!(format_countdown(time_limit), "2:00");
+        assert_eq!(format_countdown(Duration::from_millis(59_001)), "1:00");
+        assert_eq!(format_countdown(Duration::from_secs(0)), "0:00");
+    }
+
+    #[test]
+    fn test_survival() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = PlayingState::new(
+            (24, 16),
+            GameSetup::new(GameMode::Survival, None),
+            Difficulty::Normal,
+            plain_rules(),
+            TICK,
+            &clock,
+            GameRng::from_state(7),
+        );
+        assert!(playing_state.pickups.is_empty());
+
+        let grow_every = playing_state
+            .rules
+            .grow_every
+            .expect("A surviving snake grows on its own");
+        for _ in 1..grow_every {
+            assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        }
+        assert_eq!(playing_state.snake.length(), 5);
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.snake.length(), 6);
+        assert_eq!(playing_state.score, grow_every);
+        assert!(playing_state.pickups.is_empty());
+    }
+
+    fn new_tiny_playing_state(game_size: (u32, u32), clock: &ManualClock) -> PlayingState {
+        PlayingState::new(
+            game_size,
+            GameSetup::new(GameMode::Classic, None),
+            Difficulty::Normal,
+            plain_rules(),
+            TICK,
+            clock,
+            GameRng::from_state(11),
+        )
+    }
+
+    #[test]
+    fn test_win_when_the_last_cell_is_eaten() {
+        let recording = RecordingAudio::default();
+        let mut audio = Audio::new(Box::new(recording.clone()), AudioSettings::default());
+        let clock = ManualClock::default();
+        // a single row of six cells, five of them taken by the snake
+        let mut playing_state = new_tiny_playing_state((8, 4), &clock);
+        assert_eq!(playing_state.pickups[0].position, (5, 0).into());
+
+        match step(&mut playing_state, &clock, &mut audio) {
+            Some(GameFlow::Win) => (),
+            _ => panic!("Expected a win"),
+        }
+        assert_eq!(playing_state.score, 1 + 6);
+        assert_eq!(recording.sounds(), vec![Sound::Eat, Sound::Victory]);
+
+        let summary = playing_state.summary(GameEnd::BoardCleared, clock.now());
+        assert_eq!(summary.snake_length, 6);
+        assert_eq!(summary.end, GameEnd::BoardCleared);
+    }
+
+    #[test]
+    fn test_win_when_hazards_hold_the_last_cells() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        // two rows of six cells, the snake fills the first one and bombs the second
+        let mut playing_state = new_tiny_playing_state((8, 5), &clock);
+        playing_state.pickups = vec![Pickup::new(PickupKind::Cherry, (5, 0))];
+        for x in 0..6 {
+            playing_state
+                .pickups
+                .push(Pickup::new(PickupKind::Bomb, (x, 1)));
+        }
+
+        match step(&mut playing_state, &clock, &mut audio) {
+            Some(GameFlow::Win) => (),
+            _ => panic!("Expected a win"),
+        }
+    }
+
+    #[test]
+    fn test_no_win_while_poison_can_be_eaten() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        // as above, but the last free cell holds poison instead of a bomb
+        let mut playing_state = new_tiny_playing_state((8, 5), &clock);
+        playing_state.pickups = vec![
+            Pickup::new(PickupKind::Cherry, (5, 0)),
+            Pickup::new(PickupKind::Poison, (5, 1)),
+        ];
+        for x in 0..5 {
+            playing_state
+                .pickups
+                .push(Pickup::new(PickupKind::Bomb, (x, 1)));
+        }
+
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+    }
+
+    #[test]
+    fn test_cherries_come_back() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+
+        // the hazard held the cell the cherry was missing, it comes up once the hazard is gone
+        playing_state.pickups =
+            vec![Pickup::new(PickupKind::Poison, (0, 5)).with_lifetime(Some(1))];
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.pickups.len(), 1);
+        assert_eq!(playing_state.pickups[0].pickup_kind, PickupKind::Cherry);
+    }
+
+    #[test]
+    fn test_drive_tiny_board_to_completion() {
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let clock = ManualClock::default();
+        // two rows of six cells, the snake goes around them until it fills both
+        let mut playing_state = new_tiny_playing_state((8, 5), &clock);
+
+        for _ in 0..1000 {
+            let head = playing_state.snake.head();
+            let direction = match (head.x, head.y) {
+                (5, 0) => Direction::Down,
+                (0, 1) => Direction::Up,
+                (_, 0) => Direction::Right,
+                _ => Direction::Left,
+            };
+            playing_state.snake.set_next_direction(direction);
+
+            match step(&mut playing_state, &clock, &mut audio) {
+                None => (),
+                Some(GameFlow::Win) => {
+                    assert_eq!(playing_state.snake.length(), 12);
+                    assert_eq!(playing_state.cherries_eaten, 7);
+                    assert_eq!(playing_state.score, 7 + 12);
+                    return;
+                }
+                Some(_) => panic!("The snake crashed"),
+            }
+        }
+
+        panic!("The board was never filled");
+    }
+
+    #[test]
+    fn test_gameplay_sounds() {
+        let recording = RecordingAudio::default();
+        let mut audio = Audio::new(Box::new(recording.clone()), AudioSettings::default());
+        let clock = ManualClock::default();
+        let mut playing_state = new_playing_state(&clock);
+
+        // the snake starts at (4, 0) heading right
+        playing_state.pickups = vec![Pickup::new(PickupKind::Cherry, (5, 0))];
+        playing_state.score = POINTS_PER_LEVEL - 1;
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert_eq!(playing_state.level, 2);
+
+        playing_state.snake.set_next_direction(Direction::Down);
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+
+        playing_state.snake.set_next_direction(Direction::Right);
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+
+        // back on the top row, the next move hits the border
+        playing_state.snake.set_next_direction(Direction::Up);
+        assert!(step(&mut playing_state, &clock, &mut audio).is_none());
+        assert!(step(&mut playing_state, &clock, &mut audio).is_some());
+
+        assert_eq!(
+            recording.sounds(),
+            vec![
+                Sound::Eat,
+                Sound::LevelUp,
+                Sound::Turn,
+                Sound::Turn,
+                Sound::Turn,
+                Sound::Death
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remaining_parts() {
+        let duration = Duration::from_millis(1000);
+
+        assert_eq!(remaining_parts(5, Duration::from_millis(0), duration), 5);
+        assert_eq!(remaining_parts(5, Duration::from_millis(500), duration), 3);
+        assert_eq!(remaining_parts(5, Duration::from_millis(1000), duration), 1);
+        // the head never crumbles
+        assert_eq!(remaining_parts(5, Duration::from_millis(3000), duration), 1);
+        assert_eq!(remaining_parts(1, Duration::from_millis(500), duration), 1);
+    }
+
+    #[test]
+    fn test_describe_death() {
+        assert_ne!(
+            describe_death(&Collision::Border((0, -1).into())),
+            describe_death(&Collision::Body((0, 0).into()))
+        );
+    }
+
+    #[test]
+    fn test_menu() {
+        let recording = RecordingAudio::default();
+        let mut audio = Audio::new(Box::new(recording.clone()), AudioSettings::default());
+
+        let mut menu_state = MenuState::new(false);
+        assert_eq!(
+            menu_state.items,
+            vec![
+                MenuItem::NewGame,
+                MenuItem::Daily,
+                MenuItem::EnterSeed,
+                MenuItem::Stats
+            ]
+        );
+
+        let mut menu_state_with_save = MenuState::new(true);
+        assert!(handle_key_press_menu(
+            &mut menu_state_with_save,
+            &Key::Up,
+            GameMode::Classic,
+            &mut audio
+        )
+        .is_none());
+        assert_eq!(
+            menu_state_with_save.items[menu_state_with_save.selected],
+            MenuItem::Stats
+        );
+        match handle_key_press_menu(
+            &mut menu_state_with_save,
+            &Key::Return,
+            GameMode::Classic,
+            &mut audio,
+        ) {
+            Some(GameFlow::ShowStats) => (),
+            _ => panic!("Expected the statistics"),
+        }
+        assert!(handle_key_press_menu(
+            &mut menu_state_with_save,
+            &Key::S,
+            GameMode::Classic,
+            &mut audio
+        )
+        .is_none());
+        match handle_key_press_menu(
+            &mut menu_state_with_save,
+            &Key::Return,
+            GameMode::Classic,
+            &mut audio,
+        ) {
+            Some(GameFlow::Continue) => (),
+            _ => panic!("Expected to continue"),
+        }
+
+        match handle_key_press_menu(&mut menu_state, &Key::Space, GameMode::Classic, &mut audio) {
+            Some(GameFlow::StartNew(GameSetup {
+                mode: GameMode::Classic,
+                seed: None,
+            })) => (),
+            _ => panic!("Expected a new game"),
+        }
+        assert_eq!(recording.sounds(), vec![Sound::MenuMove, Sound::MenuMove]);
+    }
+
+    #[test]
+    fn test_seed_entry() {
+        let mut seed_entry_state = SeedEntryState {
+            code: String::new(),
+        };
+        assert!(handle_key_press_seed_entry(
+            &mut seed_entry_state,
+            &Key::Return,
+            GameMode::Classic
+        )
+        .is_none());
+
+        for key in &[Key::A, Key::Minus, Key::D7, Key::X, Key::Backspace, Key::B] {
+            assert!(
+                handle_key_press_seed_entry(&mut seed_entry_state, key, GameMode::Classic)
+                    .is_none()
+            );
+        }
+        assert_eq!(seed_entry_state.code, "A7B");
+        for _ in 0..MAX_SEED_CODE_LENGTH {
+            handle_key_press_seed_entry(&mut seed_entry_state, &Key::C, GameMode::Classic);
+        }
+        assert_eq!(seed_entry_state.code.len(), MAX_SEED_CODE_LENGTH);
+
+        seed_entry_state.code = "A7B".to_string();
+        match handle_key_press_seed_entry(&mut seed_entry_state, &Key::Return, GameMode::Classic) {
+            Some(GameFlow::StartNew(setup)) => {
+                assert_eq!(
+                    setup,
+                    GameSetup::new(GameMode::Classic, Some("A7B".to_string()))
+                )
+            }
+            _ => panic!("Expected a seeded game"),
+        }
+    }
+
+    #[test]
+    fn test_seeded_games_ignore_the_settings() {
+        let config =
+            Config::parse("width = 40\nheight = 30\nfood_count = 3\ntail_rule = strict").unwrap();
+        let settings = GameSettings::from_config(OpenGL::V3_2, SPRITE_SIZE, &config).unwrap();
+
+        let random = GameSetup::new(GameMode::Classic, None);
+        assert_eq!(settings.game_size_for(&random), (40, 30).into());
+        assert_eq!(settings.rules_for(&random).food_count, 3);
+        assert_eq!(settings.tail_rule_for(&random), TailRule::Strict);
+
+        let seeded = GameSetup::new(GameMode::Daily, Some("A7B".to_string()));
+        assert_eq!(settings.game_size_for(&seeded), DEFAULT_GAME_SIZE.into());
+        assert_eq!(settings.rules_for(&seeded), GameRules::default());
+        assert_eq!(settings.tail_rule_for(&seeded), TailRule::default());
+    }
+
+    #[test]
+    fn test_m_is_typed_in_seed_entry() {
+        let mut state = GameState::SeedEntry(SeedEntryState {
+            code: String::new(),
+        });
+        assert!(state.takes_text());
+        assert!(!GameState::new_menu(false).takes_text());
+
+        if let GameState::SeedEntry(seed_entry_state) = &mut state {
+            handle_key_press_seed_entry(seed_entry_state, &Key::M, GameMode::Classic);
+            assert_eq!(seed_entry_state.code, "M");
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_pickups() {
+        let clock = ManualClock::default();
+        let new_seeded = |seed: &str| {
+            GameState::new_playing(
+                (12, 8),
+                GameSetup::new(GameMode::Classic, Some(seed.to_string())),
+                Difficulty::Normal,
+                plain_rules(),
+                TICK,
+                &clock,
+            )
+        };
+        let cherry = |state: &GameState| match state {
+            GameState::Playing(playing_state) => playing_state.pickups[0].position,
+            _ => panic!("Expected a game"),
+        };
+
+        assert_eq!(cherry(&new_seeded("APPLE")), cherry(&new_seeded("APPLE")));
+        let cherries: Vec<_> = ["A", "B", "C", "D"]
+            .iter()
+            .map(|seed| cherry(&new_seeded(seed)))
+            .collect();
+        assert!(cherries.iter().any(|position| *position != cherries[0]));
+    }
+
+    #[test]
+    fn test_save_and_continue() {
+        let clock = ManualClock::default();
+        let mut audio = Audio::new(Box::new(NullAudio), AudioSettings::default());
+        let mut original = new_playing_state(&clock);
+
+        for direction in &[Direction::Down, Direction::Right, Direction::Down] {
+            original.snake.set_next_direction(*direction);
+            step(&mut original, &clock, &mut audio);
+        }
+        original.snake.set_next_direction(Direction::Left);
+        // the rules and the growth come from the save, not from the settings
+        original.snake.set_tail_rule(TailRule::Permissive);
+        original.snake.grow();
+        original.rules.cherry_lifetime = Some(50);
+
+        let snapshot = original.snapshot(clock.now());
+        let text = snapshot.to_text();
+        let restored_clock = ManualClock::default();
+        let mut restored =
+            PlayingState::restore(Snapshot::parse(&text).unwrap(), TICK, &restored_clock);
+        assert_eq!(restored.snapshot(restored_clock.now()), snapshot);
+        assert_eq!(restored.ticks, 3);
+
+        // both games go on the same way, including where the pickups come up
+        for _ in 0..4 {
+            original.pickups[0].position = original.snake.head().offset((-1, 0));
+            restored.pickups[0].position = restored.snake.head().offset((-1, 0));
+            assert!(step(&mut original, &clock, &mut audio).is_none());
+            assert!(step(&mut restored, &restored_clock, &mut audio).is_none());
+            assert_eq!(
+                restored.snapshot(restored_clock.now()),
+                original.snapshot(clock.now())
+            );
         }
     }
 }
@@ -0,0 +1,355 @@
+use crate::config::Config;
+use crate::game::events::{GameEvent, GameEventListener};
+use crate::game::mode::Difficulty;
+use crate::game::save::{parse_list, SaveError};
+use crate::size::Size;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+pub const ACHIEVEMENTS_FILE: &str = "achievements.cfg";
+
+/// What has to happen in a single game to unlock an achievement.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Goal {
+    CherriesInGame(u32),
+    /// The snake covers the percentage of the playing field.
+    BoardFilled(u32),
+    SurvivedOn(Difficulty, Duration),
+    BoardCleared,
+    /// The game is won, by clearing the board or lasting out the time limit, without going back
+    /// to the left half of the field after leaving it. The snake starts in the left half and a
+    /// cleared board takes the head through every cell, so only a time limit can be outlasted
+    /// this way.
+    WonOnTheRight,
+}
+
+pub struct Achievement {
+    /// Name used in the files, which doesn't change with the wording on the screen.
+    pub key: &'static str,
+    pub title: &'static str,
+    pub goal: Goal,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        key: "first_bite",
+        title: "First bite",
+        goal: Goal::CherriesInGame(1),
+    },
+    Achievement {
+        key: "cherry_picker",
+        title: "Cherry picker",
+        goal: Goal::CherriesInGame(50),
+    },
+    Achievement {
+        key: "half_full",
+        title: "Half full",
+        goal: Goal::BoardFilled(50),
+    },
+    Achievement {
+        key: "survivor",
+        title: "Survivor",
+        goal: Goal::SurvivedOn(Difficulty::Hard, Duration::from_secs(5 * 60)),
+    },
+    Achievement {
+        key: "clean_sweep",
+        title: "Clean sweep",
+        goal: Goal::BoardCleared,
+    },
+    Achievement {
+        key: "right_handed",
+        title: "Right-handed",
+        goal: Goal::WonOnTheRight,
+    },
+];
+
+/// What happened so far in the current game, as far as the goals are concerned.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameProgress {
+    difficulty: Difficulty,
+    field_size: Size,
+    cherries_eaten: u32,
+    snake_length: usize,
+    play_time: Duration,
+    left_the_left_half: bool,
+    returned_to_the_left_half: bool,
+    board_cleared: bool,
+    time_up: bool,
+}
+
+/// Unlocked achievements of a profile and the progress towards the others in the current game.
+#[derive(Default)]
+pub struct Achievements {
+    unlocked: BTreeSet<String>,
+    progress: Option<GameProgress>,
+    // unlocked in the current game and not yet announced
+    announcements: Vec<&'static Achievement>,
+}
+
+impl GameProgress {
+    pub fn new(difficulty: Difficulty, field_size: Size, snake_length: usize) -> Self {
+        GameProgress {
+            difficulty,
+            field_size,
+            cherries_eaten: 0,
+            snake_length,
+            play_time: Duration::from_secs(0),
+            left_the_left_half: false,
+            returned_to_the_left_half: false,
+            board_cleared: false,
+            time_up: false,
+        }
+    }
+
+    /// Progress of a continued game, which doesn't know where the snake went before the save.
+    pub fn continued(
+        difficulty: Difficulty,
+        field_size: Size,
+        snake_length: usize,
+        cherries_eaten: u32,
+    ) -> Self {
+        GameProgress {
+            cherries_eaten,
+            returned_to_the_left_half: true,
+            ..GameProgress::new(difficulty, field_size, snake_length)
+        }
+    }
+
+    fn reached(&self, goal: Goal) -> bool {
+        match goal {
+            Goal::CherriesInGame(count) => self.cherries_eaten >= count,
+            Goal::BoardFilled(percent) => {
+                let area = (self.field_size.width * self.field_size.height) as usize;
+                self.snake_length * 100 >= area * percent as usize
+            }
+            Goal::SurvivedOn(difficulty, time) => {
+                self.difficulty == difficulty && self.play_time >= time
+            }
+            Goal::BoardCleared => self.board_cleared,
+            Goal::WonOnTheRight => {
+                !self.returned_to_the_left_half && (self.board_cleared || self.time_up)
+            }
+        }
+    }
+
+    fn moved(&mut self, column: i32, snake_length: usize) {
+        self.snake_length = snake_length;
+
+        let in_left_half = column < (self.field_size.width / 2) as i32;
+        match (in_left_half, self.left_the_left_half) {
+            (false, _) => self.left_the_left_half = true,
+            (true, true) => self.returned_to_the_left_half = true,
+            (true, false) => (),
+        }
+    }
+}
+
+impl GameEventListener for Achievements {
+    fn on_event(&mut self, event: &GameEvent) {
+        let progress = match &mut self.progress {
+            Some(progress) => progress,
+            None => return,
+        };
+
+        match event {
+            GameEvent::AteFood { .. } => progress.cherries_eaten += 1,
+            GameEvent::Moved { position, length } => progress.moved(position.x, *length),
+            GameEvent::Won { .. } => progress.board_cleared = true,
+            GameEvent::TimeUp { .. } => progress.time_up = true,
+            _ => return,
+        }
+
+        self.check();
+    }
+}
+
+impl Achievements {
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        let config = Config::parse(text)?;
+
+        // keys which this version doesn't know are kept, they may come from a newer one
+        let unlocked = parse_list(&config, "unlocked", |words| match words {
+            [key] => Some(key.to_string()),
+            _ => None,
+        })?;
+
+        Ok(Achievements {
+            unlocked: unlocked.into_iter().collect(),
+            ..Achievements::default()
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let unlocked: Vec<&str> = self.unlocked.iter().map(String::as_str).collect();
+
+        [
+            "# Unlocked achievements of the profile".to_string(),
+            format!("unlocked = {}", unlocked.join(", ")),
+        ]
+        .join("\n")
+            + "\n"
+    }
+
+    pub fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.unlocked.contains(achievement.key)
+    }
+
+    pub fn unlocked_count(&self) -> usize {
+        ACHIEVEMENTS
+            .iter()
+            .filter(|achievement| self.is_unlocked(achievement))
+            .count()
+    }
+
+    /// Starts tracking a game, until the next one starts or `end_game` is called.
+    pub fn start_game(&mut self, progress: GameProgress) {
+        self.progress = Some(progress);
+    }
+
+    pub fn end_game(&mut self) {
+        self.progress = None;
+    }
+
+    pub fn update_play_time(&mut self, play_time: Duration) {
+        if let Some(progress) = &mut self.progress {
+            progress.play_time = play_time;
+            self.check();
+        }
+    }
+
+    /// Achievements unlocked since the last call.
+    pub fn take_announcements(&mut self) -> Vec<&'static Achievement> {
+        self.announcements.drain(..).collect()
+    }
+
+    fn check(&mut self) {
+        let progress = match &self.progress {
+            Some(progress) => progress,
+            None => return,
+        };
+
+        for achievement in ACHIEVEMENTS {
+            if !self.unlocked.contains(achievement.key) && progress.reached(achievement.goal) {
+                self.unlocked.insert(achievement.key.to_string());
+                self.announcements.push(achievement);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(achievements: &[&Achievement]) -> Vec<&'static str> {
+        achievements
+            .iter()
+            .map(|achievement| achievement.key)
+            .collect()
+    }
+
+    fn ate(achievements: &mut Achievements, count: u32) {
+        for _ in 0..count {
+            achievements.on_event(&GameEvent::AteFood {
+                position: (0, 0).into(),
+                points: 1,
+            });
+        }
+    }
+
+    fn moved(achievements: &mut Achievements, x: i32, length: usize) {
+        achievements.on_event(&GameEvent::Moved {
+            position: (x, 0).into(),
+            length,
+        });
+    }
+
+    #[test]
+    fn test_unlock_once() {
+        let mut achievements = Achievements::default();
+        // nothing is tracked outside of a game
+        ate(&mut achievements, 1);
+        assert!(achievements.take_announcements().is_empty());
+
+        achievements.start_game(GameProgress::new(Difficulty::Normal, (10, 6).into(), 5));
+        ate(&mut achievements, 1);
+        assert_eq!(keys(&achievements.take_announcements()), vec!["first_bite"]);
+
+        achievements.start_game(GameProgress::new(Difficulty::Normal, (10, 6).into(), 5));
+        ate(&mut achievements, 1);
+        assert!(achievements.take_announcements().is_empty());
+        assert_eq!(achievements.unlocked_count(), 1);
+    }
+
+    #[test]
+    fn test_goals() {
+        let mut achievements = Achievements::default();
+        achievements.start_game(GameProgress::new(Difficulty::Normal, (10, 6).into(), 5));
+        moved(&mut achievements, 5, 29);
+        assert!(achievements.take_announcements().is_empty());
+        moved(&mut achievements, 6, 30);
+        assert_eq!(keys(&achievements.take_announcements()), vec!["half_full"]);
+
+        // survival only counts on hard
+        achievements.update_play_time(Duration::from_secs(600));
+        assert!(achievements.take_announcements().is_empty());
+        achievements.start_game(GameProgress::new(Difficulty::Hard, (10, 6).into(), 5));
+        achievements.update_play_time(Duration::from_secs(299));
+        assert!(achievements.take_announcements().is_empty());
+        achievements.update_play_time(Duration::from_secs(300));
+        assert_eq!(keys(&achievements.take_announcements()), vec!["survivor"]);
+    }
+
+    #[test]
+    fn test_won_on_the_right() {
+        let time_up = GameEvent::TimeUp {
+            position: (5, 0).into(),
+        };
+
+        let mut achievements = Achievements::default();
+        achievements.start_game(GameProgress::new(Difficulty::Normal, (10, 6).into(), 5));
+        moved(&mut achievements, 4, 5);
+        moved(&mut achievements, 5, 5);
+        moved(&mut achievements, 4, 5);
+        moved(&mut achievements, 5, 5);
+        achievements.on_event(&time_up);
+        assert!(achievements.take_announcements().is_empty());
+
+        // only a won game counts
+        achievements.start_game(GameProgress::new(Difficulty::Normal, (10, 6).into(), 5));
+        moved(&mut achievements, 4, 5);
+        moved(&mut achievements, 5, 5);
+        ate(&mut achievements, 20);
+        assert_eq!(keys(&achievements.take_announcements()), vec!["first_bite"]);
+        achievements.on_event(&time_up);
+        assert_eq!(
+            keys(&achievements.take_announcements()),
+            vec!["right_handed"]
+        );
+
+        // a continued game may have been to the left half before
+        achievements.unlocked.clear();
+        achievements.start_game(GameProgress::continued(
+            Difficulty::Normal,
+            (10, 6).into(),
+            5,
+            0,
+        ));
+        moved(&mut achievements, 5, 5);
+        achievements.on_event(&time_up);
+        assert!(achievements.take_announcements().is_empty());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut achievements = Achievements::parse("unlocked = survivor, from_the_future").unwrap();
+        achievements.start_game(GameProgress::new(Difficulty::Normal, (10, 6).into(), 5));
+        ate(&mut achievements, 1);
+
+        let text = achievements.to_text();
+        assert!(text.contains("unlocked = first_bite, from_the_future, survivor\n"));
+        assert_eq!(Achievements::parse(&text).unwrap().unlocked_count(), 2);
+
+        assert!(Achievements::parse("unlocked = two words").is_err());
+    }
+}
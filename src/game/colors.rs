@@ -1 +0,0 @@
-pub const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
@@ -0,0 +1,364 @@
+use crate::game::events::{GameEvent, GameEventListener};
+use crate::game::font::{draw_string_at, Font};
+use crate::game::palette;
+use crate::game::text::text_width;
+use crate::point::Point;
+use crate::sprite_batch::Sprite;
+use crate::sprite_renderer::GenericContext;
+use graphics::types::Color;
+use rand::rngs::SmallRng;
+use rand::{FromEntropy, Rng};
+use std::f64::consts::PI;
+
+const PARTICLE_SIZE: f64 = 0.25;
+const PARTICLE_DRAG: f64 = 3.0;
+const FLOATING_TEXT_LIFETIME: f64 = 0.8;
+const FLOATING_TEXT_SPEED: f64 = 1.5;
+
+struct Particle {
+    position: [f64; 2],
+    velocity: [f64; 2],
+    age: f64,
+    lifetime: f64,
+    color: Color,
+}
+
+struct FloatingText {
+    text: String,
+    position: [f64; 2],
+    age: f64,
+    color: Color,
+}
+
+struct Flash {
+    color: Color,
+    age: f64,
+    duration: f64,
+}
+
+struct Shake {
+    magnitude: f64,
+    age: f64,
+    duration: f64,
+}
+
+/// Short-lived visual feedback: particles, floating texts, screen flash and shake.
+///
+/// Times are measured in seconds and positions in cells of the playing field.
+pub struct Effects {
+    particles: Vec<Particle>,
+    texts: Vec<FloatingText>,
+    flash: Option<Flash>,
+    shake: Option<Shake>,
+    rng: SmallRng,
+}
+
+fn center_of(cell: Point) -> [f64; 2] {
+    [cell.x as f64 + 0.5, cell.y as f64 + 0.5]
+}
+
+/// Goes from 1 down to 0 over the lifetime.
+fn fade(age: f64, lifetime: f64) -> f32 {
+    (1.0 - age / lifetime).clamp(0.0, 1.0) as f32
+}
+
+impl GameEventListener for Effects {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::AteFood { position, points } => {
+                self.burst(*position, 10, 3.0, palette::RED);
+                self.float_text(format!("+{}", points), *position, palette::YELLOW);
+            }
+            GameEvent::PoweredUp { position, power_up } => {
+                self.burst(*position, 10, 3.0, power_up.color());
+                self.float_text(
+                    power_up.key().replace('_', " "),
+                    *position,
+                    power_up.color(),
+                );
+            }
+            GameEvent::Poisoned { position, points } => {
+                self.burst(*position, 10, 3.0, palette::GREEN);
+                self.float_text(format!("-{}", points), *position, palette::GREEN);
+            }
+            GameEvent::Turned { .. } | GameEvent::Moved { .. } | GameEvent::TimeUp { .. } => (),
+            GameEvent::PickupSpawned { position } => {
+                self.burst(*position, 6, 1.5, palette::YELLOW);
+            }
+            GameEvent::PickupExpired { position } => {
+                self.burst(*position, 6, 1.0, palette::GRAY);
+            }
+            GameEvent::LevelUp { position, level } => {
+                self.float_text(format!("level {}", level), *position, palette::WHITE);
+                self.flash(palette::with_alpha(palette::YELLOW, 0.3), 0.3);
+            }
+            GameEvent::Won { position } => {
+                self.burst(*position, 40, 5.0, palette::YELLOW);
+                self.flash(palette::with_alpha(palette::YELLOW, 0.5), 0.6);
+            }
+            GameEvent::Died { position, .. } => {
+                self.burst(*position, 30, 6.0, palette::WHITE);
+                self.flash(palette::with_alpha(palette::RED, 0.5), 0.3);
+                self.shake(0.4, 0.5);
+            }
+        }
+    }
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Effects {
+            particles: Vec::new(),
+            texts: Vec::new(),
+            flash: None,
+            shake: None,
+            rng: SmallRng::from_entropy(),
+        }
+    }
+
+    /// Throws particles in all directions from the center of the cell.
+    pub fn burst(&mut self, cell: Point, count: usize, speed: f64, color: Color) {
+        for _ in 0..count {
+            let angle = self.rng.gen_range(0.0, 2.0 * PI);
+            let speed = speed * self.rng.gen_range(0.5, 1.0);
+
+            self.particles.push(Particle {
+                position: center_of(cell),
+                velocity: [angle.cos() * speed, angle.sin() * speed],
+                age: 0.0,
+                lifetime: self.rng.gen_range(0.4, 0.8),
+                color,
+            });
+        }
+    }
+
+    /// Shows a text rising from the cell.
+    pub fn float_text(&mut self, text: String, cell: Point, color: Color) {
+        let [x, y] = center_of(cell);
+        let width = text_width(&text) as f64;
+
+        self.texts.push(FloatingText {
+            text,
+            position: [x - width / 2.0, y - 1.0],
+            age: 0.0,
+            color,
+        });
+    }
+
+    /// Tints the whole field, fading out over the duration.
+    pub fn flash(&mut self, color: Color, duration: f64) {
+        self.flash = Some(Flash {
+            color,
+            age: 0.0,
+            duration,
+        });
+    }
+
+    /// Shakes the screen by up to `magnitude` cells, calming down over the duration.
+    pub fn shake(&mut self, magnitude: f64, duration: f64) {
+        self.shake = Some(Shake {
+            magnitude,
+            age: 0.0,
+            duration,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.texts.clear();
+        self.flash = None;
+        self.shake = None;
+    }
+
+    /// Advances all effects by `dt` seconds and drops the finished ones.
+    pub fn update(&mut self, dt: f64) {
+        let drag = (1.0 - PARTICLE_DRAG * dt).max(0.0);
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.position[0] += particle.velocity[0] * dt;
+            particle.position[1] += particle.velocity[1] * dt;
+            particle.velocity[0] *= drag;
+            particle.velocity[1] *= drag;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        for text in &mut self.texts {
+            text.age += dt;
+            text.position[1] -= FLOATING_TEXT_SPEED * dt;
+        }
+        self.texts.retain(|text| text.age < FLOATING_TEXT_LIFETIME);
+
+        if let Some(flash) = &mut self.flash {
+            flash.age += dt;
+            if flash.age >= flash.duration {
+                self.flash = None;
+            }
+        }
+
+        if let Some(shake) = &mut self.shake {
+            shake.age += dt;
+            if shake.age >= shake.duration {
+                self.shake = None;
+            }
+        }
+    }
+
+    /// Current displacement of the screen in cells.
+    pub fn shake_offset(&self) -> [f64; 2] {
+        match &self.shake {
+            Some(shake) => {
+                let strength = shake.magnitude * fade(shake.age, shake.duration) as f64;
+                // two unrelated frequencies so that the screen doesn't just move diagonally
+                [
+                    strength * (shake.age * 47.0).sin(),
+                    strength * (shake.age * 61.0).cos(),
+                ]
+            }
+            None => [0.0, 0.0],
+        }
+    }
+
+    pub fn render<C>(&self, context: &mut C, solid: &Sprite, font: &Font)
+    where
+        C: GenericContext,
+    {
+        for particle in &self.particles {
+            let [x, y] = particle.position;
+            let alpha = fade(particle.age, particle.lifetime);
+            context.draw_sprite_at(
+                [x - PARTICLE_SIZE / 2.0, y - PARTICLE_SIZE / 2.0],
+                [PARTICLE_SIZE, PARTICLE_SIZE],
+                solid,
+                palette::with_alpha(particle.color, alpha),
+            );
+        }
+
+        for text in &self.texts {
+            let alpha = fade(text.age, FLOATING_TEXT_LIFETIME);
+            draw_string_at(
+                context,
+                &text.text,
+                text.position,
+                palette::with_alpha(text.color, alpha),
+                font,
+            );
+        }
+
+        if let Some(flash) = &self.flash {
+            let [red, green, blue, alpha] = flash.color;
+            let alpha = alpha * fade(flash.age, flash.duration);
+            let size = context.size();
+            context.draw_sprite_at(
+                [0.0, 0.0],
+                [size.width as f64, size.height as f64],
+                solid,
+                [red, green, blue, alpha],
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::snake::Collision;
+
+    fn is_active(effects: &Effects) -> bool {
+        !effects.particles.is_empty()
+            || !effects.texts.is_empty()
+            || effects.flash.is_some()
+            || effects.shake.is_some()
+    }
+
+    fn step(effects: &mut Effects, seconds: f64, steps: u32) {
+        for _ in 0..steps {
+            effects.update(seconds / steps as f64);
+        }
+    }
+
+    #[test]
+    fn test_events_spawn_effects() {
+        let mut effects = Effects::new();
+        assert!(!is_active(&effects));
+
+        effects.on_event(&GameEvent::AteFood {
+            position: (3, 4).into(),
+            points: 1,
+        });
+        assert_eq!(effects.particles.len(), 10);
+        assert_eq!(effects.texts.len(), 1);
+        assert_eq!(effects.texts[0].text, "+1");
+        assert!(effects.flash.is_none());
+        assert!(effects.shake.is_none());
+
+        effects.on_event(&GameEvent::Died {
+            position: (3, 4).into(),
+            cause: Collision::Body((4, 4).into()),
+        });
+        assert_eq!(effects.particles.len(), 40);
+        assert!(effects.flash.is_some());
+        assert!(effects.shake.is_some());
+    }
+
+    #[test]
+    fn test_effects_expire() {
+        let mut effects = Effects::new();
+        effects.on_event(&GameEvent::Died {
+            position: (0, 0).into(),
+            cause: Collision::Border((-1, 0).into()),
+        });
+
+        step(&mut effects, 0.35, 7);
+        assert!(effects.flash.is_none());
+        assert!(effects.shake.is_some());
+        assert!(!effects.particles.is_empty());
+
+        step(&mut effects, 0.5, 10);
+        assert!(!is_active(&effects));
+    }
+
+    #[test]
+    fn test_floating_text_rises() {
+        let mut effects = Effects::new();
+        effects.float_text("+1".to_string(), (5, 5).into(), palette::YELLOW);
+        assert_eq!(effects.texts[0].position, [4.5, 4.5]);
+
+        step(&mut effects, 0.4, 4);
+        let [x, y] = effects.texts[0].position;
+        assert_eq!(x, 4.5);
+        assert!((y - (4.5 - 0.4 * FLOATING_TEXT_SPEED)).abs() < 1e-9);
+
+        step(&mut effects, 0.5, 5);
+        assert!(effects.texts.is_empty());
+    }
+
+    #[test]
+    fn test_particles_slow_down() {
+        let mut effects = Effects::new();
+        effects.burst((0, 0).into(), 1, 4.0, palette::WHITE);
+        let [vx, vy] = effects.particles[0].velocity;
+        let speed = (vx * vx + vy * vy).sqrt();
+
+        step(&mut effects, 0.2, 4);
+        let [vx, vy] = effects.particles[0].velocity;
+        assert!((vx * vx + vy * vy).sqrt() < speed);
+    }
+
+    #[test]
+    fn test_shake_calms_down() {
+        let mut effects = Effects::new();
+        assert_eq!(effects.shake_offset(), [0.0, 0.0]);
+
+        effects.shake(1.0, 1.0);
+        // all the way up at the start
+        assert_eq!(effects.shake_offset(), [0.0, 1.0]);
+
+        step(&mut effects, 0.5, 5);
+        let [x, y] = effects.shake_offset();
+        assert!(x.abs() <= 0.5 && y.abs() <= 0.5);
+
+        step(&mut effects, 0.6, 6);
+        assert_eq!(effects.shake_offset(), [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::audio::{Audio, Sound};
+use crate::game::power_up::PowerUp;
+use crate::game::snake::Collision;
+use crate::point::Point;
+
+/// Something which happened in the simulation and may need feedback, positions are cells of the
+/// playing field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    AteFood {
+        position: Point,
+        points: u32,
+    },
+    /// A power-up was picked up, it takes effect from the next move.
+    PoweredUp {
+        position: Point,
+        power_up: PowerUp,
+    },
+    /// Poison was eaten, `points` is what it cost.
+    Poisoned {
+        position: Point,
+        points: u32,
+    },
+    Turned {
+        position: Point,
+    },
+    /// The head moved to the position, `length` counts the body parts after the move.
+    Moved {
+        position: Point,
+        length: usize,
+    },
+    PickupSpawned {
+        position: Point,
+    },
+    /// The pickup at the position outlived its lifetime and vanished.
+    PickupExpired {
+        position: Point,
+    },
+    LevelUp {
+        position: Point,
+        level: u32,
+    },
+    Died {
+        position: Point,
+        cause: Collision,
+    },
+    /// The snake filled the field, `position` is where it ate the last pickup.
+    Won {
+        position: Point,
+    },
+    /// The time limit ran out with the snake still going, `position` is where its head is.
+    TimeUp {
+        position: Point,
+    },
+}
+
+/// Anything which reacts to the simulation without being a part of it.
+pub trait GameEventListener {
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+/// Collects the events of an update, so that the simulation doesn't have to know who listens.
+#[derive(Default)]
+pub struct EventQueue {
+    events: Vec<GameEvent>,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        EventQueue { events: Vec::new() }
+    }
+
+    pub fn push(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    /// Hands every queued event to all listeners, in order, and empties the queue.
+    pub fn dispatch(&mut self, listeners: &mut [&mut dyn GameEventListener]) {
+        for event in self.events.drain(..) {
+            for listener in listeners.iter_mut() {
+                listener.on_event(&event);
+            }
+        }
+    }
+}
+
+impl GameEvent {
+    pub fn sound(&self) -> Option<Sound> {
+        match self {
+            GameEvent::AteFood { .. } => Some(Sound::Eat),
+            GameEvent::PoweredUp { .. } => Some(Sound::PowerUp),
+            GameEvent::Poisoned { .. } => Some(Sound::Poison),
+            GameEvent::Turned { .. } => Some(Sound::Turn),
+            GameEvent::Moved { .. } => None,
+            GameEvent::PickupSpawned { .. } => None,
+            GameEvent::PickupExpired { .. } => None,
+            GameEvent::LevelUp { .. } => Some(Sound::LevelUp),
+            GameEvent::Died { .. } => Some(Sound::Death),
+            GameEvent::Won { .. } => Some(Sound::Victory),
+            GameEvent::TimeUp { .. } => None,
+        }
+    }
+}
+
+impl GameEventListener for Audio {
+    fn on_event(&mut self, event: &GameEvent) {
+        if let Some(sound) = event.sound() {
+            self.play(sound);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log(Vec<GameEvent>);
+
+    impl GameEventListener for Log {
+        fn on_event(&mut self, event: &GameEvent) {
+            self.0.push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let mut queue = EventQueue::new();
+        let eaten = GameEvent::AteFood {
+            position: (1, 2).into(),
+            points: 1,
+        };
+        let died = GameEvent::Died {
+            position: (1, 2).into(),
+            cause: Collision::Border((1, -1).into()),
+        };
+        queue.push(eaten.clone());
+        queue.push(died.clone());
+
+        let mut first = Log::default();
+        let mut second = Log::default();
+        queue.dispatch(&mut [&mut first, &mut second]);
+
+        assert_eq!(first.0, vec![eaten.clone(), died.clone()]);
+        assert_eq!(second.0, vec![eaten, died]);
+
+        queue.dispatch(&mut [&mut first]);
+        assert_eq!(first.0.len(), 2);
+    }
+}
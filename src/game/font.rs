@@ -1,59 +1,266 @@
+use crate::config::{Config, ConfigError};
 use crate::point::Point;
+use crate::sprite_batch::Sprite;
 use crate::sprite_renderer::GenericContext;
-use image::{DynamicImage, GenericImageView};
-use opengl_graphics::{Texture, TextureSettings};
+use graphics::types::Color;
+use std::collections::HashMap;
+use std::str::FromStr;
 
-pub type Font = [Texture; 128];
+/// Consecutive code points drawn from consecutive cells of the font sheet.
+///
+/// Cells are numbered left to right, top to bottom, starting from 0.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlyphRange {
+    pub first: char,
+    pub last: char,
+    pub first_cell: u32,
+}
+
+/// Describes which characters the font sheet contains and where.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FontLayout {
+    pub ranges: Vec<GlyphRange>,
+    /// Cell drawn for characters outside of all ranges.
+    pub fallback_cell: u32,
+}
+
+/// Maps characters to the cells of the font sheet.
+#[derive(Debug)]
+pub struct GlyphTable {
+    cells: HashMap<char, u32>,
+    fallback_cell: u32,
+}
+
+pub struct Font {
+    glyphs: HashMap<u32, Sprite>,
+    table: GlyphTable,
+}
 
-pub fn get_font(image: &DynamicImage, image_row_length: u32, size: u32) -> Font {
-    let texture_settings = &TextureSettings::new();
+fn parse_code_point(text: &str) -> Option<char> {
+    u32::from_str_radix(text.trim(), 16)
+        .ok()
+        .and_then(std::char::from_u32)
+}
 
-    unsafe {
-        let mut textures: Font = std::mem::uninitialized();
+impl FromStr for GlyphRange {
+    type Err = ();
 
-        let mut counter = 0;
-        for texture in &mut textures[..] {
-            if counter == 0 {
-                let empty_texture =
-                    Texture::empty(texture_settings).expect("Failed to create the empty texture");
-                std::ptr::write(texture, empty_texture);
-            } else {
-                let counter = counter - 1;
-                let x = counter % image_row_length;
-                let y = counter / image_row_length;
+    /// Parses `first-last@cell`, with code points in hex, e.g. `20-7e@31`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts = text.splitn(2, '@');
+        let code_points = parts.next().ok_or(())?;
+        let first_cell = parts.next().ok_or(())?.trim().parse().map_err(|_| ())?;
 
-                let one_character_image = image.view(x * size, y * size, size, size).to_image();
-                let character_texture = Texture::from_image(&one_character_image, texture_settings);
+        let mut code_points = code_points.splitn(2, '-');
+        let first = code_points.next().and_then(parse_code_point).ok_or(())?;
+        let last = match code_points.next() {
+            Some(last) => parse_code_point(last).ok_or(())?,
+            None => first,
+        };
 
-                std::ptr::write(texture, character_texture);
+        if last < first {
+            return Err(());
+        }
+
+        Ok(GlyphRange {
+            first,
+            last,
+            first_cell,
+        })
+    }
+}
+
+impl FontLayout {
+    /// Reads the layout from `ranges` (comma-separated glyph ranges) and `fallback` keys.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let ranges = match config.get::<String>("ranges")? {
+            Some(ranges) => ranges
+                .split(',')
+                .map(|range| range.parse())
+                .collect::<Result<Vec<GlyphRange>, ()>>()
+                .map_err(|_| config.invalid_value("ranges"))?,
+            None => Vec::new(),
+        };
+
+        Ok(FontLayout {
+            ranges,
+            fallback_cell: config.get("fallback")?.unwrap_or(0),
+        })
+    }
+}
+
+impl GlyphTable {
+    pub fn new(layout: &FontLayout) -> Self {
+        let mut cells = HashMap::new();
+
+        for range in &layout.ranges {
+            for (offset, character) in (range.first..=range.last).enumerate() {
+                cells.insert(character, range.first_cell + offset as u32);
             }
-            counter += 1;
         }
 
-        textures
+        GlyphTable {
+            cells,
+            fallback_cell: layout.fallback_cell,
+        }
+    }
+
+    pub fn cell(&self, character: char) -> u32 {
+        self.cells
+            .get(&character)
+            .cloned()
+            .unwrap_or(self.fallback_cell)
+    }
+
+    fn used_cells(&self) -> impl Iterator<Item = u32> + '_ {
+        self.cells
+            .values()
+            .cloned()
+            .chain(std::iter::once(self.fallback_cell))
     }
 }
 
-fn draw_character<'a, C, P>(context: &'a mut C, character: char, position: P, font: &Font)
+impl Font {
+    /// Finds the glyphs of the font sheet placed at `origin` of the atlas.
+    ///
+    /// Cells which lie outside of the sheet are drawn as empty.
+    pub fn new(
+        layout: &FontLayout,
+        origin: [u32; 2],
+        sheet_size: [u32; 2],
+        size: u32,
+        atlas_size: [u32; 2],
+    ) -> Self {
+        let [origin_x, origin_y] = origin;
+        let columns = sheet_size[0] / size;
+        let rows = sheet_size[1] / size;
+
+        let table = GlyphTable::new(layout);
+        let mut glyphs = HashMap::new();
+
+        for cell in table.used_cells() {
+            if glyphs.contains_key(&cell) || cell >= columns * rows {
+                continue;
+            }
+
+            let x = origin_x + cell % columns * size;
+            let y = origin_y + cell / columns * size;
+            glyphs.insert(cell, Sprite::new([x, y, size, size], atlas_size));
+        }
+
+        Font { glyphs, table }
+    }
+
+    fn glyph(&self, character: char) -> Option<&Sprite> {
+        self.glyphs.get(&self.table.cell(character))
+    }
+}
+
+pub fn draw_string<C, S, P>(context: &mut C, string: S, position: P, color: Color, font: &Font)
 where
     C: GenericContext,
+    S: AsRef<str>,
     P: Into<Point>,
 {
-    let ascii_code = character as u8;
-    let character_sprite = &font[ascii_code as usize];
-    context.draw_sprite(position, character_sprite);
+    let position: Point = position.into();
+    draw_string_at(
+        context,
+        string,
+        [position.x as f64, position.y as f64],
+        color,
+        font,
+    );
 }
 
-pub fn draw_string<'a, C, S, P>(context: &'a mut C, string: S, position: P, font: &Font)
-where
+/// Draws the string at a position which doesn't have to be aligned to cells.
+pub fn draw_string_at<C, S>(
+    context: &mut C,
+    string: S,
+    position: [f64; 2],
+    color: Color,
+    font: &Font,
+) where
     C: GenericContext,
     S: AsRef<str>,
-    P: Into<Point>,
 {
-    let position: Point = position.into();
+    let [x, y] = position;
     let string = string.as_ref().to_uppercase();
     for (index, character) in string.chars().enumerate() {
-        let char_position = position.offset((index as i32, 0));
-        draw_character(context, character, char_position, font);
+        if let Some(character_sprite) = font.glyph(character) {
+            context.draw_sprite_at([x + index as f64, y], [1.0, 1.0], character_sprite, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ascii_layout() -> FontLayout {
+        FontLayout {
+            ranges: vec![GlyphRange {
+                first: '\u{1}',
+                last: '\u{7f}',
+                first_cell: 0,
+            }],
+            fallback_cell: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_glyph_range() {
+        assert_eq!(
+            "20-7e@31".parse(),
+            Ok(GlyphRange {
+                first: ' ',
+                last: '~',
+                first_cell: 31,
+            })
+        );
+        assert_eq!(
+            "410@128".parse(),
+            Ok(GlyphRange {
+                first: '\u{410}',
+                last: '\u{410}',
+                first_cell: 128,
+            })
+        );
+        assert_eq!("7e-20@31".parse::<GlyphRange>(), Err(()));
+        assert_eq!("20-7e".parse::<GlyphRange>(), Err(()));
+        assert_eq!("d800@0".parse::<GlyphRange>(), Err(()));
+    }
+
+    #[test]
+    fn test_layout_from_config() {
+        let config = Config::parse("ranges = 1-7f@0, 410-42f@128\nfallback = 5").unwrap();
+        let layout = FontLayout::from_config(&config).unwrap();
+
+        assert_eq!(layout.ranges.len(), 2);
+        assert_eq!(layout.ranges[1].first, '\u{410}');
+        assert_eq!(layout.fallback_cell, 5);
+
+        let config = Config::parse("ranges = 1-7f@zero").unwrap();
+        assert!(FontLayout::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_glyph_table() {
+        let table = GlyphTable::new(&ascii_layout());
+
+        assert_eq!(table.cell('\u{1}'), 0);
+        assert_eq!(table.cell(' '), 31);
+        assert_eq!(table.cell('A'), 64);
+        assert_eq!(table.cell('\u{7f}'), 126);
+    }
+
+    #[test]
+    fn test_glyph_table_fallback() {
+        let table = GlyphTable::new(&ascii_layout());
+
+        assert_eq!(table.cell('\u{0}'), 0);
+        assert_eq!(table.cell('\u{80}'), 0);
+        // would be 'A' when truncated to a byte
+        assert_eq!(table.cell('\u{141}'), 0);
+        assert_eq!(table.cell('Ж'), 0);
     }
 }
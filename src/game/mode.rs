@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+const TIME_ATTACK_LIMIT: Duration = Duration::from_secs(120);
+// moves after which a surviving snake grows by a part
+const SURVIVAL_GROW_EVERY: u32 = 10;
+
+/// Rules a game is played by, best scores are kept for each of them separately.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum GameMode {
+    Classic,
+    /// Classic rules with the pickups seeded by the date, the same for everybody on the day. Like
+    /// any seeded game it is played on the default board by the default rules, whatever the
+    /// settings say.
+    Daily,
+    /// As many points as possible before the time runs out.
+    TimeAttack,
+    /// No food, the snake grows on its own and scores a point for every move it survives.
+    Survival,
+}
+
+impl GameMode {
+    /// Name used in the files, which doesn't change with the wording on the screen.
+    pub fn key(self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::Daily => "daily",
+            GameMode::TimeAttack => "time_attack",
+            GameMode::Survival => "survival",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "classic" => Some(GameMode::Classic),
+            "daily" => Some(GameMode::Daily),
+            "time_attack" => Some(GameMode::TimeAttack),
+            "survival" => Some(GameMode::Survival),
+            _ => None,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Daily => "Daily",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Survival => "Survival",
+        }
+    }
+}
+
+/// How fast the snake moves, independently of the level.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn key(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    /// Time between two moves on the first level.
+    pub fn tick_duration(self, base: Duration) -> Duration {
+        let scale = match self {
+            Difficulty::Easy => 1.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.6,
+        };
+        Duration::from_secs_f64(base.as_secs_f64() * scale)
+    }
+}
+
+/// What appears on the field, read from the settings.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GameRules {
+    /// Number of cherries on the field at once.
+    pub food_count: u32,
+    /// Chance of a power-up coming up when a cherry is eaten, from 0 to 1.
+    pub power_up_chance: f64,
+    /// Chance of poison, a bomb or spikes coming up when a cherry is eaten, from 0 to 1.
+    pub hazard_chance: f64,
+    /// Moves a cherry stays on the field for, cherries stay until eaten when there is none.
+    pub cherry_lifetime: Option<u32>,
+    /// Whether cherries step around the field.
+    pub wandering_cherries: bool,
+    /// Pairs of portals placed at the start of a game.
+    pub portal_pairs: u32,
+    /// Play time after which the game is over, it goes on until the snake crashes when there is
+    /// none.
+    pub time_limit: Option<Duration>,
+    /// Moves after which the snake grows by a part on its own. It then scores the moves it
+    /// survives instead of the pickups.
+    pub grow_every: Option<u32>,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            food_count: 1,
+            power_up_chance: 0.2,
+            hazard_chance: 0.1,
+            cherry_lifetime: None,
+            wandering_cherries: false,
+            portal_pairs: 1,
+            time_limit: None,
+            grow_every: None,
+        }
+    }
+}
+
+impl GameRules {
+    /// The rules adjusted to what the mode is played by.
+    pub fn for_mode(self, mode: GameMode) -> Self {
+        match mode {
+            GameMode::Classic | GameMode::Daily => self,
+            GameMode::TimeAttack => GameRules {
+                time_limit: Some(TIME_ATTACK_LIMIT),
+                ..self
+            },
+            GameMode::Survival => GameRules {
+                food_count: 0,
+                grow_every: Some(SURVIVAL_GROW_EVERY),
+                ..self
+            },
+        }
+    }
+}
+
+/// What a new game is played with, chosen on the menu.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameSetup {
+    pub mode: GameMode,
+    /// Code which the pickups are placed by, they are random when there is none.
+    pub seed: Option<String>,
+}
+
+impl GameSetup {
+    pub fn new(mode: GameMode, seed: Option<String>) -> Self {
+        GameSetup { mode, seed }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rules_for_mode() {
+        let rules = GameRules::default();
+        assert_eq!(rules.for_mode(GameMode::Daily), rules);
+
+        let time_attack = rules.for_mode(GameMode::TimeAttack);
+        assert_eq!(time_attack.time_limit, Some(TIME_ATTACK_LIMIT));
+        assert_eq!(time_attack.food_count, rules.food_count);
+
+        let survival = rules.for_mode(GameMode::Survival);
+        assert_eq!(survival.food_count, 0);
+        assert_eq!(survival.grow_every, Some(SURVIVAL_GROW_EVERY));
+        assert_eq!(survival.time_limit, None);
+
+        assert_eq!(
+            GameMode::from_key(GameMode::TimeAttack.key()),
+            Some(GameMode::TimeAttack)
+        );
+    }
+}
@@ -0,0 +1,359 @@
+use crate::point::Point;
+use crate::size::Size;
+use rand::Rng;
+
+// slot of the cells which are taken
+const TAKEN: u32 = u32::MAX;
+
+/// Cells of the field which are taken, kept up to date as things move, so that a random free
+/// cell is picked with a single random number.
+///
+/// The free cells are kept in a list, in an order which depends on how they were taken and
+/// freed. A continued game puts the order back with `set_free_order`, so that it places its
+/// pickups like the original.
+pub struct OccupancyGrid {
+    size: Size,
+    // index of every cell in `free_cells`, `TAKEN` for the taken ones
+    slots: Vec<u32>,
+    free_cells: Vec<Point>,
+}
+
+impl OccupancyGrid {
+    /// Grid with every cell free.
+    pub fn new<S: Into<Size>>(size: S) -> Self {
+        let size: Size = size.into();
+        let area = size.width * size.height;
+
+        let mut grid = OccupancyGrid {
+            size,
+            slots: (0..area).collect(),
+            free_cells: Vec::with_capacity(area as usize),
+        };
+        for index in 0..area as usize {
+            let point = grid.point(index);
+            grid.free_cells.push(point);
+        }
+        grid
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn free_count(&self) -> u32 {
+        self.free_cells.len() as u32
+    }
+
+    /// Cells outside of the grid count as occupied.
+    pub fn is_occupied(&self, point: Point) -> bool {
+        self.slot(point).is_none()
+    }
+
+    /// Marks the cell as taken, cells outside of the grid are ignored.
+    pub fn occupy(&mut self, point: Point) {
+        let slot = match self.slot(point) {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        // the last free cell moves into the place of the taken one
+        self.free_cells.swap_remove(slot);
+        if let Some(moved) = self.free_cells.get(slot).cloned() {
+            let index = self
+                .index(moved)
+                .expect("Free cells lie inside of the grid");
+            self.slots[index] = slot as u32;
+        }
+        let index = self.index(point).expect("The cell has a slot");
+        self.slots[index] = TAKEN;
+    }
+
+    pub fn free(&mut self, point: Point) {
+        let index = match self.index(point) {
+            Some(index) if self.slots[index] == TAKEN => index,
+            _ => return,
+        };
+
+        self.slots[index] = self.free_cells.len() as u32;
+        self.free_cells.push(point);
+    }
+
+    /// Free cells in the order they are picked from.
+    pub fn free_cells(&self) -> &[Point] {
+        &self.free_cells
+    }
+
+    /// Puts the free cells in the order returned by `free_cells`. Returns `false` and keeps the
+    /// order when the cells aren't the free ones.
+    pub fn set_free_order(&mut self, cells: &[Point]) -> bool {
+        let mut seen = vec![false; self.slots.len()];
+        let valid = cells.len() == self.free_cells.len()
+            && cells
+                .iter()
+                .all(|cell| match (self.index(*cell), self.slot(*cell)) {
+                    (Some(index), Some(_)) if !seen[index] => {
+                        seen[index] = true;
+                        true
+                    }
+                    _ => false,
+                });
+        if !valid {
+            return false;
+        }
+
+        for (slot, cell) in cells.iter().enumerate() {
+            let index = self.index(*cell).expect("The cell was checked");
+            self.slots[index] = slot as u32;
+        }
+        self.free_cells = cells.to_vec();
+        true
+    }
+
+    /// Any free cell with the same chance, or `None` when the grid is full.
+    pub fn random_free_cell<R: Rng>(&self, rng: &mut R) -> Option<Point> {
+        self.random_free_cell_avoiding(&[], rng)
+    }
+
+    /// Any free cell other than the avoided ones with the same chance, or `None` when there is
+    /// no such cell. Takes a single random number, the few avoided cells are stepped over.
+    pub fn random_free_cell_avoiding<R: Rng>(
+        &self,
+        avoided: &[Point],
+        rng: &mut R,
+    ) -> Option<Point> {
+        let mut skipped: Vec<usize> = avoided.iter().filter_map(|cell| self.slot(*cell)).collect();
+        skipped.sort_unstable();
+        skipped.dedup();
+
+        let count = self.free_cells.len() - skipped.len();
+        if count == 0 {
+            return None;
+        }
+
+        let mut pick = rng.gen_range(0, count);
+        for slot in skipped {
+            if slot <= pick {
+                pick += 1;
+            }
+        }
+        Some(self.free_cells[pick])
+    }
+
+    /// Place of the cell in the free cells, `None` when it is taken or outside of the grid.
+    fn slot(&self, point: Point) -> Option<usize> {
+        match self.index(point).map(|index| self.slots[index]) {
+            Some(TAKEN) | None => None,
+            Some(slot) => Some(slot as usize),
+        }
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        let inside = point.x >= 0
+            && point.y >= 0
+            && (point.x as u32) < self.size.width
+            && (point.y as u32) < self.size.height;
+
+        match inside {
+            true => Some((point.y as u32 * self.size.width + point.x as u32) as usize),
+            false => None,
+        }
+    }
+
+    fn point(&self, index: usize) -> Point {
+        let width = self.size.width as usize;
+        ((index % width) as i32, (index / width) as i32).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::rng::GameRng;
+    use std::collections::HashSet;
+    use std::time::Instant;
+
+    fn random_point(rng: &mut GameRng, size: Size) -> Point {
+        (
+            rng.gen_range(-1, size.width as i32 + 1),
+            rng.gen_range(-1, size.height as i32 + 1),
+        )
+            .into()
+    }
+
+    #[test]
+    fn test_occupy_and_free() {
+        let mut grid = OccupancyGrid::new((3, 2));
+        assert_eq!(grid.free_count(), 6);
+
+        grid.occupy((1, 1).into());
+        grid.occupy((1, 1).into());
+        assert!(grid.is_occupied((1, 1).into()));
+        assert!(!grid.is_occupied((0, 1).into()));
+        assert!(grid.is_occupied((3, 0).into()));
+        assert_eq!(grid.free_count(), 5);
+
+        grid.free((1, 1).into());
+        grid.free((1, 1).into());
+        grid.occupy((-1, 0).into());
+        assert_eq!(grid.free_count(), 6);
+    }
+
+    #[test]
+    fn test_last_free_cell() {
+        let mut rng = GameRng::from_state(0);
+        let mut grid = OccupancyGrid::new((2, 2));
+        for &cell in &[(0, 0), (1, 0), (1, 1)] {
+            grid.occupy(cell.into());
+        }
+
+        assert_eq!(grid.random_free_cell(&mut rng), Some((0, 1).into()));
+
+        grid.occupy((0, 1).into());
+        assert_eq!(grid.random_free_cell(&mut rng), None);
+    }
+
+    /// Random changes checked against a plain set of the taken cells.
+    #[test]
+    fn test_matches_a_set() {
+        let mut rng = GameRng::from_state(41);
+
+        for &(width, height) in &[(1, 1), (2, 3), (7, 5), (16, 16)] {
+            let size: Size = (width, height).into();
+            let mut grid = OccupancyGrid::new(size);
+            let mut taken = HashSet::new();
+
+            for _ in 0..2000 {
+                let point = random_point(&mut rng, size);
+                match rng.gen_range(0, 3) {
+                    0 => {
+                        grid.free(point);
+                        taken.remove(&(point.x, point.y));
+                    }
+                    _ => {
+                        grid.occupy(point);
+                        if grid.index(point).is_some() {
+                            taken.insert((point.x, point.y));
+                        }
+                    }
+                }
+
+                assert_eq!(
+                    grid.free_count() as usize,
+                    (width * height) as usize - taken.len()
+                );
+                match grid.random_free_cell(&mut rng) {
+                    Some(cell) => {
+                        assert!(!grid.is_occupied(cell));
+                        assert!(!taken.contains(&(cell.x, cell.y)));
+                    }
+                    None => assert_eq!(grid.free_count(), 0),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_free_order() {
+        let mut first = OccupancyGrid::new((5, 5));
+        let mut second = OccupancyGrid::new((5, 5));
+        for x in 0..5 {
+            for y in 0..5 {
+                first.occupy((x, y).into());
+                second.occupy((4 - x, 4 - y).into());
+            }
+        }
+        for &cell in &[(1, 1), (3, 2), (0, 4), (2, 2)] {
+            first.free(cell.into());
+        }
+        first.occupy((3, 2).into());
+        for &cell in &[(0, 4), (1, 1), (2, 2)] {
+            second.free(cell.into());
+        }
+        assert_ne!(first.free_cells(), second.free_cells());
+
+        // the same order picks the same cells
+        assert!(second.set_free_order(first.free_cells()));
+        let mut first_rng = GameRng::from_state(3);
+        let mut second_rng = GameRng::from_state(3);
+        for _ in 0..20 {
+            assert_eq!(
+                first.random_free_cell(&mut first_rng),
+                second.random_free_cell(&mut second_rng)
+            );
+        }
+
+        // only an order of the free cells is taken
+        let order = second.free_cells().to_vec();
+        assert!(!second.set_free_order(&[(1, 1).into(), (0, 4).into()]));
+        assert!(!second.set_free_order(&[(1, 1).into(), (0, 4).into(), (3, 2).into()]));
+        assert!(!second.set_free_order(&[(1, 1).into(), (0, 4).into(), (1, 1).into()]));
+        assert_eq!(second.free_cells(), &order[..]);
+    }
+
+    #[test]
+    fn test_avoiding() {
+        let mut rng = GameRng::from_state(9);
+        let mut grid = OccupancyGrid::new((3, 3));
+        grid.occupy((1, 1).into());
+        let avoided: Vec<Point> = vec![(0, 0).into(), (1, 1).into(), (2, 2).into(), (0, 0).into()];
+
+        let picked: HashSet<(i32, i32)> = (0..500)
+            .filter_map(|_| grid.random_free_cell_avoiding(&avoided, &mut rng))
+            .map(|cell| (cell.x, cell.y))
+            .collect();
+        assert_eq!(picked.len(), 6);
+        assert!(!picked.contains(&(0, 0)) && !picked.contains(&(2, 2)));
+
+        let all: Vec<Point> = grid.free_cells().to_vec();
+        assert_eq!(grid.random_free_cell_avoiding(&all, &mut rng), None);
+    }
+
+    #[test]
+    fn test_every_free_cell_comes_up() {
+        let mut rng = GameRng::from_state(5);
+        let mut grid = OccupancyGrid::new((4, 4));
+        grid.occupy((0, 0).into());
+        grid.occupy((2, 3).into());
+
+        let picked: HashSet<(i32, i32)> = (0..1000)
+            .filter_map(|_| grid.random_free_cell(&mut rng))
+            .map(|cell| (cell.x, cell.y))
+            .collect();
+        assert_eq!(picked.len(), 14);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_large_board() {
+        let side = 1000_u32;
+        let mut rng = GameRng::from_state(1);
+
+        let start = Instant::now();
+        let mut grid = OccupancyGrid::new((side, side));
+        println!("{0}x{0} grid built in {1:?}", side, start.elapsed());
+
+        // a snake covering most of the board, moving and eating
+        let start = Instant::now();
+        for index in 0..side * side * 9 / 10 {
+            grid.occupy(((index % side) as i32, (index / side) as i32).into());
+        }
+        println!("filled 90% in {:?}", start.elapsed());
+
+        let moves = 1_000_000;
+        let start = Instant::now();
+        for _ in 0..moves {
+            let head = grid
+                .random_free_cell(&mut rng)
+                .expect("The grid has free cells");
+            grid.occupy(head);
+            let tail = random_point(&mut rng, (side, side).into());
+            grid.free(tail);
+        }
+        println!(
+            "{} moves with a free cell picked each time in {:?}",
+            moves,
+            start.elapsed()
+        );
+    }
+}
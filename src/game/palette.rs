@@ -0,0 +1,70 @@
+use graphics::types::Color;
+use std::time::Duration;
+
+pub const BLACK: Color = [0.0, 0.0, 0.0, 1.0];
+pub const WHITE: Color = [1.0, 1.0, 1.0, 1.0];
+pub const GRAY: Color = [0.6, 0.6, 0.6, 1.0];
+pub const RED: Color = [0.9, 0.2, 0.2, 1.0];
+pub const YELLOW: Color = [1.0, 0.85, 0.3, 1.0];
+pub const GREEN: Color = [0.4, 0.9, 0.3, 1.0];
+
+/// Tints which tell the snakes apart, the first one leaves the sprites as drawn.
+pub const SNAKE_COLORS: [Color; 4] = [
+    WHITE,
+    [0.5, 0.8, 1.0, 1.0],
+    [1.0, 0.6, 0.3, 1.0],
+    [1.0, 0.5, 0.9, 1.0],
+];
+
+/// Colors which tell the pairs of portals apart.
+pub const PORTAL_COLORS: [Color; 3] = [
+    [0.3, 0.6, 1.0, 1.0],
+    [1.0, 0.5, 0.1, 1.0],
+    [0.7, 0.4, 1.0, 1.0],
+];
+
+/// Tint of the snake flashing after it crashed.
+pub const DEATH_FLASH: Color = RED;
+
+pub fn snake_color(index: usize) -> Color {
+    SNAKE_COLORS[index % SNAKE_COLORS.len()]
+}
+
+pub fn portal_color(index: usize) -> Color {
+    PORTAL_COLORS[index % PORTAL_COLORS.len()]
+}
+
+pub fn with_alpha(color: Color, alpha: f32) -> Color {
+    let [red, green, blue, _] = color;
+    [red, green, blue, alpha]
+}
+
+/// Alternates between two colors, switching every `period`.
+pub fn blink(first: Color, second: Color, elapsed: Duration, period: Duration) -> Color {
+    let period = period.as_millis().max(1);
+    match (elapsed.as_millis() / period) % 2 {
+        0 => first,
+        _ => second,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blink() {
+        let period = Duration::from_millis(100);
+        assert_eq!(blink(RED, WHITE, Duration::from_millis(0), period), RED);
+        assert_eq!(blink(RED, WHITE, Duration::from_millis(99), period), RED);
+        assert_eq!(blink(RED, WHITE, Duration::from_millis(100), period), WHITE);
+        assert_eq!(blink(RED, WHITE, Duration::from_millis(250), period), RED);
+    }
+
+    #[test]
+    fn test_snake_color() {
+        assert_eq!(snake_color(0), WHITE);
+        assert_ne!(snake_color(1), snake_color(2));
+        assert_eq!(snake_color(SNAKE_COLORS.len()), snake_color(0));
+    }
+}
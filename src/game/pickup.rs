@@ -1,136 +1,239 @@
+use crate::game::font::draw_string;
+use crate::game::occupancy::OccupancyGrid;
+use crate::game::palette;
+use crate::game::power_up::PowerUp;
+use crate::game::snake::Direction;
 use crate::game::snake_sprite::SpriteData;
 use crate::point::Point;
-use crate::size::Size;
 use crate::sprite_renderer::GenericContext;
 
-use rand::{thread_rng, Rng};
-use std::collections::HashSet;
+use rand::Rng;
 
-fn get_hashcode(x: u32, y: u32) -> u32 {
-    (17 * 31 + x) * 31 + y
-}
+// an expiring pickup blinks for its last few moves
+const BLINK_MOVES: u32 = 6;
+// points an expiring cherry is worth on top of the usual one while it is fresh
+const FRESH_BONUS: u32 = 2;
 
-fn find_non_occupied_cell<'a, S, P>(field_size: S, occupied_cells: &'a [P]) -> Option<Point>
-where
-    S: Into<Size>,
-    P: Into<Point>,
-    &'a P: Into<Point>,
-{
-    let field_size: Size = field_size.into();
-    let space_size = field_size.width * field_size.height;
-    let rand_range = space_size - occupied_cells.len() as u32;
-
-    if rand_range <= 0 {
-        return None;
-    }
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PickupKind {
+    Cherry,
+    Apple,
+    PowerUp(PowerUp),
+    /// Cuts the tail and costs points.
+    Poison,
+    /// Ends the game when eaten.
+    Bomb,
+    /// Rolls across the field in the direction and ends the game when it meets the head.
+    Spikes(Direction),
+}
 
-    let occupied_cells_hashset: HashSet<u32> = occupied_cells
-        .iter()
-        .map(|point| {
-            let point: Point = point.into();
-            get_hashcode(point.x as u32, point.y as u32)
-        })
-        .collect();
-
-    let mut rng = thread_rng();
-    let empty_cell_index: u32 = rng.gen_range(0, rand_range);
-
-    let mut target_cell_index = 0;
-
-    for i in 0..=empty_cell_index {
-        // loop until we find the empty cell and increase the
-        loop {
-            let target_cell_y = target_cell_index / field_size.width;
-            let target_cell_x = target_cell_index % field_size.width;
-            let target_cell_hashcode = get_hashcode(target_cell_x, target_cell_y);
-            if !occupied_cells_hashset.contains(&target_cell_hashcode) {
-                break;
-            }
-            target_cell_index += 1;
+impl PickupKind {
+    /// Whether the pickup is there to be avoided.
+    pub fn is_hazard(self) -> bool {
+        match self {
+            PickupKind::Poison | PickupKind::Bomb | PickupKind::Spikes(_) => true,
+            PickupKind::Cherry | PickupKind::Apple | PickupKind::PowerUp(_) => false,
         }
+    }
 
-        if i == empty_cell_index {
-            let target_cell_y = target_cell_index / field_size.width;
-            let target_cell_x = target_cell_index % field_size.width;
-            return Some((target_cell_x as i32, target_cell_y as i32).into());
+    /// Whether running into the pickup ends the game, poison only shrinks the snake.
+    pub fn is_lethal(self) -> bool {
+        match self {
+            PickupKind::Bomb | PickupKind::Spikes(_) => true,
+            PickupKind::Poison
+            | PickupKind::Cherry
+            | PickupKind::Apple
+            | PickupKind::PowerUp(_) => false,
         }
-
-        target_cell_index += 1;
     }
-
-    None
-}
-
-pub enum PickupKind {
-    Cherry,
-    Apple,
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Pickup {
     pub pickup_kind: PickupKind,
     pub position: Point,
+    /// Moves since the pickup came up, counted only when it has a lifetime.
+    pub age: u32,
+    /// Moves after which the pickup vanishes, it stays until eaten when there is none.
+    pub lifetime: Option<u32>,
+    /// Whether the pickup steps to a free cell next to it on every move.
+    pub wanders: bool,
+}
+
+/// Random cell which is neither occupied nor taken by one of the pickups, `None` when there is
+/// none left.
+fn free_cell<R: Rng>(occupancy: &OccupancyGrid, pickups: &[Pickup], rng: &mut R) -> Option<Point> {
+    let taken: Vec<Point> = pickups.iter().map(|pickup| pickup.position).collect();
+    occupancy.random_free_cell_avoiding(&taken, rng)
 }
 
 impl Pickup {
-    pub fn new_cherry<'a, S, P>(
-        field_size: S,
-        occupied_cells: &'a [P],
-    ) -> Option<Self>
-    where
-        S: Into<Size>,
-        P: Into<Point>,
-        &'a P: Into<Point>
-    {
-        find_non_occupied_cell(field_size, occupied_cells).map(|position| Pickup {
-            pickup_kind: PickupKind::Cherry,
-            position,
-        })
+    /// Pickup which stays where it is until eaten.
+    pub fn new<P: Into<Point>>(pickup_kind: PickupKind, position: P) -> Self {
+        Pickup {
+            pickup_kind,
+            position: position.into(),
+            age: 0,
+            lifetime: None,
+            wanders: false,
+        }
     }
 
-    pub fn new_apple<'a, S, P>(
-        field_size: S,
-        occupied_cells: &'a [P],
-    ) -> Option<Self>
-    where
-        S: Into<Size>,
-        P: Into<Point>,
-        &'a P: Into<Point>,
-    {
-        find_non_occupied_cell(field_size, occupied_cells).map(|position| Pickup {
-            pickup_kind: PickupKind::Apple,
-            position,
-        })
+    pub fn with_lifetime(self, lifetime: Option<u32>) -> Self {
+        Pickup { lifetime, ..self }
+    }
+
+    pub fn wandering(self, wanders: bool) -> Self {
+        Pickup { wanders, ..self }
+    }
+
+    /// Counts a move, returns whether the pickup has vanished with it.
+    pub fn grow_older(&mut self) -> bool {
+        match self.lifetime {
+            Some(lifetime) => {
+                self.age += 1;
+                self.age >= lifetime
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the pickup is about to vanish.
+    pub fn is_expiring(&self) -> bool {
+        match self.lifetime {
+            Some(lifetime) => self.age + BLINK_MOVES >= lifetime,
+            None => false,
+        }
+    }
+
+    /// Points of a cherry, an expiring one is worth more the fresher it is.
+    pub fn points(&self) -> u32 {
+        match self.lifetime {
+            Some(lifetime) => 1 + FRESH_BONUS * lifetime.saturating_sub(self.age) / lifetime.max(1),
+            None => 1,
+        }
+    }
+
+    /// Places the pickup on a random free cell away from the other pickups, returns `None` when
+    /// there is none.
+    pub fn new_cherry<R: Rng>(
+        occupancy: &OccupancyGrid,
+        pickups: &[Pickup],
+        rng: &mut R,
+    ) -> Option<Self> {
+        free_cell(occupancy, pickups, rng).map(|position| Pickup::new(PickupKind::Cherry, position))
+    }
+
+    /// Places a pickup of any kind on a random free cell away from the other pickups, returns
+    /// `None` when there is none.
+    pub fn new_random<R: Rng>(
+        pickup_kind: PickupKind,
+        occupancy: &OccupancyGrid,
+        pickups: &[Pickup],
+        rng: &mut R,
+    ) -> Option<Self> {
+        free_cell(occupancy, pickups, rng).map(|position| Pickup::new(pickup_kind, position))
     }
 
     pub fn render<C>(&self, context: &mut C, all_sprites: &SpriteData)
     where
         C: GenericContext,
     {
-        let texture = match &self.pickup_kind {
-            PickupKind::Cherry => &all_sprites.cherry,
-            PickupKind::Apple => &all_sprites.apple,
+        // blinks by the move before it vanishes
+        if self.is_expiring() && self.age % 2 == 1 {
+            return;
+        }
+
+        // there are no sprites for the others, symbols stand in
+        let (symbol, color) = match &self.pickup_kind {
+            PickupKind::Cherry => {
+                return context.draw_sprite(self.position, &all_sprites.cherry);
+            }
+            PickupKind::Apple => return context.draw_sprite(self.position, &all_sprites.apple),
+            PickupKind::PowerUp(power_up) => (power_up.symbol(), power_up.color()),
+            PickupKind::Poison => ("%", palette::GREEN),
+            PickupKind::Bomb => ("*", palette::RED),
+            PickupKind::Spikes(_) => ("#", palette::GRAY),
         };
 
-        context.draw_sprite((self.position.x, self.position.y), texture);
+        draw_string(context, symbol, self.position, color, &all_sprites.font);
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::game::rng::GameRng;
 
     #[test]
-    fn test_find_non_occupied_cell() {
-        let cells: Vec<(i32, i32)> = vec![(0, 0), (1, 0), (1, 1)];
+    fn test_new_cherry_on_free_cell() {
+        let mut rng = GameRng::from_state(0);
+        let occupancy = |cells: &[(i32, i32)]| {
+            let mut occupancy = OccupancyGrid::new((2, 2));
+            for &cell in cells {
+                occupancy.occupy(cell.into());
+            }
+            occupancy
+        };
+
+        let cherry = |cells: &[(i32, i32)], rng: &mut GameRng| {
+            Pickup::new_cherry(&occupancy(cells), &[], rng).map(|pickup| pickup.position)
+        };
+        assert_eq!(
+            cherry(&[(0, 0), (1, 0), (1, 1)], &mut rng),
+            Some((0, 1).into())
+        );
         assert_eq!(
-            find_non_occupied_cell((2, 2), &cells),
-            Some((0_i32, 1_i32).into())
+            cherry(&[(0, 0), (1, 0), (0, 1)], &mut rng),
+            Some((1, 1).into())
         );
+        assert_eq!(cherry(&[(0, 0), (1, 0), (0, 1), (1, 1)], &mut rng), None);
+    }
+
+    #[test]
+    fn test_new_cherry_away_from_other_pickups() {
+        let mut rng = GameRng::from_state(0);
+        let mut occupancy = OccupancyGrid::new((2, 2));
+        occupancy.occupy((0, 0).into());
+        let mut pickups = Vec::new();
+
+        for _ in 0..3 {
+            let cherry = Pickup::new_cherry(&occupancy, &pickups, &mut rng)
+                .expect("There is a free cell without a pickup");
+            assert!(!occupancy.is_occupied(cherry.position));
+            assert!(pickups
+                .iter()
+                .all(|pickup: &Pickup| pickup.position != cherry.position));
+            pickups.push(cherry);
+        }
 
-        let cells: Vec<(i32, i32)> = vec![(0, 0), (1, 0), (0, 1)];
-        assert_eq!(find_non_occupied_cell((2, 2), &cells), Some((1, 1).into()));
+        assert!(Pickup::new_cherry(&occupancy, &pickups, &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_lifetime() {
+        let mut cherry = Pickup::new(PickupKind::Cherry, (0, 0)).with_lifetime(Some(10));
+        assert_eq!(cherry.points(), 3);
 
-        let cells: Vec<(i32, i32)> = vec![(0, 0), (1, 0), (0, 1), (1, 1)];
-        assert_eq!(find_non_occupied_cell((2, 2), &cells), None);
+        for _ in 0..3 {
+            assert!(!cherry.grow_older());
+        }
+        assert!(!cherry.is_expiring());
+        assert_eq!(cherry.points(), 2);
+
+        for _ in 0..6 {
+            assert!(!cherry.grow_older());
+        }
+        assert!(cherry.is_expiring());
+        assert_eq!(cherry.points(), 1);
+        assert!(cherry.grow_older());
+
+        // without a lifetime it stays for good
+        let mut cherry = Pickup::new(PickupKind::Cherry, (0, 0));
+        for _ in 0..100 {
+            assert!(!cherry.grow_older());
+        }
+        assert_eq!((cherry.age, cherry.points()), (0, 1));
+        assert!(!cherry.is_expiring());
     }
 }
@@ -0,0 +1,123 @@
+use crate::game::font::{draw_string, Font};
+use crate::game::occupancy::OccupancyGrid;
+use crate::point::Point;
+use crate::sprite_renderer::GenericContext;
+use graphics::types::Color;
+
+use rand::Rng;
+
+// random cells tried for a pair before giving up on it
+const PLACEMENT_TRIES: u32 = 100;
+
+/// Two cells which lead into each other, the snake going into one comes out past the other.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PortalPair {
+    pub ends: [Point; 2],
+}
+
+/// Whether the cells touch by a side.
+fn adjacent(first: Point, second: Point) -> bool {
+    (first.x - second.x).abs() + (first.y - second.y).abs() == 1
+}
+
+impl PortalPair {
+    pub fn new<P: Into<Point>>(first: P, second: P) -> Self {
+        PortalPair {
+            ends: [first.into(), second.into()],
+        }
+    }
+
+    /// The other end when the cell is one of the ends.
+    pub fn other_end(&self, cell: Point) -> Option<Point> {
+        match self.ends {
+            [first, second] if first == cell => Some(second),
+            [first, second] if second == cell => Some(first),
+            _ => None,
+        }
+    }
+
+    /// Whether the cell is an end of the pair or lies next to one.
+    pub fn is_near(&self, cell: Point) -> bool {
+        self.ends
+            .iter()
+            .any(|end| *end == cell || adjacent(*end, cell))
+    }
+
+    /// Places a pair on free cells away from the border and from the other portals, so that
+    /// the snake always comes out onto the field. Returns `None` when no such cells turn up.
+    pub fn new_random<R: Rng>(
+        occupancy: &OccupancyGrid,
+        portals: &[PortalPair],
+        rng: &mut R,
+    ) -> Option<Self> {
+        let size = occupancy.size();
+        let suitable = |cell: Point, taken: &[PortalPair]| {
+            cell.x > 0
+                && cell.y > 0
+                && cell.x < size.width as i32 - 1
+                && cell.y < size.height as i32 - 1
+                && taken.iter().all(|portal| !portal.is_near(cell))
+        };
+
+        let mut random_cell = |taken: &[PortalPair]| {
+            (0..PLACEMENT_TRIES)
+                .filter_map(|_| occupancy.random_free_cell(rng))
+                .find(|cell| suitable(*cell, taken))
+        };
+
+        let first = random_cell(portals)?;
+        // the ends keep apart as well, coming out next to the entrance would be no jump at all
+        let mut taken = portals.to_vec();
+        taken.push(PortalPair::new(first, first));
+        let second = random_cell(&taken)?;
+
+        Some(PortalPair::new(first, second))
+    }
+
+    pub fn render<C>(&self, context: &mut C, color: Color, font: &Font)
+    where
+        C: GenericContext,
+    {
+        for end in &self.ends {
+            draw_string(context, "O", *end, color, font);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::rng::GameRng;
+
+    #[test]
+    fn test_other_end() {
+        let portal = PortalPair::new((1, 1), (5, 3));
+        assert_eq!(portal.other_end((1, 1).into()), Some((5, 3).into()));
+        assert_eq!(portal.other_end((5, 3).into()), Some((1, 1).into()));
+        assert_eq!(portal.other_end((1, 2).into()), None);
+        assert!(portal.is_near((5, 2).into()));
+        assert!(!portal.is_near((4, 2).into()));
+    }
+
+    #[test]
+    fn test_new_random_keeps_apart() {
+        let mut rng = GameRng::from_state(3);
+        let occupancy = OccupancyGrid::new((8, 8));
+        let mut portals: Vec<PortalPair> = Vec::new();
+
+        for _ in 0..3 {
+            let portal = PortalPair::new_random(&occupancy, &portals, &mut rng)
+                .expect("The field has room for the portals");
+            for end in &portal.ends {
+                assert!(end.x > 0 && end.y > 0 && end.x < 7 && end.y < 7);
+                assert!(portals.iter().all(|other| !other.is_near(*end)));
+            }
+            assert!(!adjacent(portal.ends[0], portal.ends[1]));
+            portals.push(portal);
+        }
+
+        // a field with no inner cells has no room for any
+        let occupancy = OccupancyGrid::new((2, 8));
+        assert_eq!(PortalPair::new_random(&occupancy, &[], &mut rng), None);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::game::palette;
+use graphics::types::Color;
+
+// picking up a power-up which is still active adds its duration, up to this many in total
+const MAX_STACKED_DURATIONS: u32 = 2;
+
+/// Pickups which change the rules of the game for a while, or at once for the shrink.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PowerUp {
+    /// The snake moves at half the speed.
+    SlowMotion,
+    /// The tail is cut by a few parts.
+    Shrink,
+    /// The snake passes through its own body.
+    Ghost,
+    /// Cherries are worth twice the points.
+    DoublePoints,
+}
+
+pub const POWER_UPS: [PowerUp; 4] = [
+    PowerUp::SlowMotion,
+    PowerUp::Shrink,
+    PowerUp::Ghost,
+    PowerUp::DoublePoints,
+];
+
+impl PowerUp {
+    /// Name used in the files.
+    pub fn key(self) -> &'static str {
+        match self {
+            PowerUp::SlowMotion => "slow_motion",
+            PowerUp::Shrink => "shrink",
+            PowerUp::Ghost => "ghost",
+            PowerUp::DoublePoints => "double_points",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        POWER_UPS
+            .iter()
+            .cloned()
+            .find(|power_up| power_up.key() == key)
+    }
+
+    /// Number of moves the power-up lasts for, `None` for the shrink which happens at once.
+    pub fn duration(self) -> Option<u32> {
+        match self {
+            PowerUp::SlowMotion => Some(20),
+            PowerUp::Shrink => None,
+            PowerUp::Ghost => Some(12),
+            PowerUp::DoublePoints => Some(30),
+        }
+    }
+
+    /// Character the power-up is drawn with, on the field and next to its duration bar.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            PowerUp::SlowMotion => "S",
+            PowerUp::Shrink => "-",
+            PowerUp::Ghost => "G",
+            PowerUp::DoublePoints => "2",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            PowerUp::SlowMotion => palette::SNAKE_COLORS[1],
+            PowerUp::Shrink => palette::SNAKE_COLORS[2],
+            PowerUp::Ghost => palette::GRAY,
+            PowerUp::DoublePoints => palette::YELLOW,
+        }
+    }
+}
+
+/// Power-ups in effect and the moves they have left, in the order they were picked up.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ActivePowerUps {
+    active: Vec<(PowerUp, u32)>,
+}
+
+impl ActivePowerUps {
+    pub fn new() -> Self {
+        ActivePowerUps::default()
+    }
+
+    /// Puts back the power-ups as returned by `remaining`, the ones with no moves left are dropped.
+    pub fn from_remaining(remaining: Vec<(PowerUp, u32)>) -> Self {
+        let mut power_ups = ActivePowerUps::new();
+        for (power_up, moves) in remaining {
+            if moves > 0 && power_up.duration().is_some() && !power_ups.is_active(power_up) {
+                power_ups.active.push((power_up, moves));
+            }
+        }
+        power_ups
+    }
+
+    /// Starts the power-up, or extends it when it is already active. Power-ups without a
+    /// duration are not kept.
+    pub fn activate(&mut self, power_up: PowerUp) {
+        let duration = match power_up.duration() {
+            Some(duration) => duration,
+            None => return,
+        };
+
+        match self
+            .active
+            .iter_mut()
+            .find(|(active, _)| *active == power_up)
+        {
+            Some((_, moves)) => *moves = (*moves + duration).min(duration * MAX_STACKED_DURATIONS),
+            None => self.active.push((power_up, duration)),
+        }
+    }
+
+    pub fn is_active(&self, power_up: PowerUp) -> bool {
+        self.active.iter().any(|(active, _)| *active == power_up)
+    }
+
+    /// Counts a move off every power-up, returns the ones which ran out.
+    pub fn tick(&mut self) -> Vec<PowerUp> {
+        for (_, moves) in &mut self.active {
+            *moves -= 1;
+        }
+
+        let expired = self
+            .active
+            .iter()
+            .filter(|(_, moves)| *moves == 0)
+            .map(|(power_up, _)| *power_up)
+            .collect();
+        self.active.retain(|(_, moves)| *moves > 0);
+        expired
+    }
+
+    /// Active power-ups with the moves they have left.
+    pub fn remaining(&self) -> impl Iterator<Item = (PowerUp, u32)> + '_ {
+        self.active.iter().cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expiry() {
+        let mut power_ups = ActivePowerUps::new();
+        power_ups.activate(PowerUp::Ghost);
+        power_ups.activate(PowerUp::Shrink);
+        assert!(power_ups.is_active(PowerUp::Ghost));
+        assert!(!power_ups.is_active(PowerUp::Shrink));
+
+        for _ in 0..11 {
+            assert!(power_ups.tick().is_empty());
+        }
+        assert_eq!(power_ups.tick(), vec![PowerUp::Ghost]);
+        assert!(!power_ups.is_active(PowerUp::Ghost));
+        assert!(power_ups.tick().is_empty());
+    }
+
+    #[test]
+    fn test_stacking() {
+        let mut power_ups = ActivePowerUps::new();
+        power_ups.activate(PowerUp::SlowMotion);
+        for _ in 0..5 {
+            power_ups.tick();
+        }
+        power_ups.activate(PowerUp::DoublePoints);
+        power_ups.activate(PowerUp::SlowMotion);
+        assert_eq!(
+            power_ups.remaining().collect::<Vec<_>>(),
+            vec![(PowerUp::SlowMotion, 35), (PowerUp::DoublePoints, 30)]
+        );
+
+        // the same power-up stacks only up to a limit
+        power_ups.activate(PowerUp::SlowMotion);
+        assert_eq!(
+            power_ups.remaining().next(),
+            Some((PowerUp::SlowMotion, 40))
+        );
+
+        let expired: Vec<PowerUp> = (0..40).flat_map(|_| power_ups.tick()).collect();
+        assert_eq!(expired, vec![PowerUp::DoublePoints, PowerUp::SlowMotion]);
+        assert_eq!(power_ups.remaining().count(), 0);
+    }
+
+    #[test]
+    fn test_from_remaining() {
+        let power_ups = ActivePowerUps::from_remaining(vec![
+            (PowerUp::Ghost, 3),
+            (PowerUp::Shrink, 3),
+            (PowerUp::SlowMotion, 0),
+            (PowerUp::Ghost, 5),
+        ]);
+        assert_eq!(
+            power_ups.remaining().collect::<Vec<_>>(),
+            vec![(PowerUp::Ghost, 3)]
+        );
+        assert_eq!(
+            PowerUp::from_key("double_points"),
+            Some(PowerUp::DoublePoints)
+        );
+        assert_eq!(PowerUp::from_key("triple_points"), None);
+    }
+}
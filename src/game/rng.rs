@@ -0,0 +1,75 @@
+use rand::{Error, RngCore};
+
+/// Small random number generator whose whole state is a single number, so that a game can be
+/// saved and continued with the same pickups coming up.
+///
+/// This is SplitMix64, good enough for placing pickups and cheap to copy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn from_state(state: u64) -> Self {
+        GameRng { state }
+    }
+
+    pub fn from_entropy() -> Self {
+        GameRng::from_state(rand::random())
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_known_sequence() {
+        // reference values of SplitMix64 seeded with 1234567
+        let mut rng = GameRng::from_state(1_234_567);
+        assert_eq!(rng.next_u64(), 6_457_827_717_110_365_317);
+        assert_eq!(rng.next_u64(), 3_203_168_211_198_807_973);
+    }
+
+    #[test]
+    fn test_continues_from_state() {
+        let mut rng = GameRng::from_state(42);
+        rng.gen_range(0, 100);
+
+        let mut copy = GameRng::from_state(rng.state());
+        let expected: Vec<u32> = (0..10).map(|_| rng.gen_range(0, 100)).collect();
+        let actual: Vec<u32> = (0..10).map(|_| copy.gen_range(0, 100)).collect();
+        assert_eq!(actual, expected);
+    }
+}
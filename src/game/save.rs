@@ -0,0 +1,700 @@
+use crate::config::{Config, ConfigError};
+use crate::game::mode::{Difficulty, GameMode, GameRules};
+use crate::game::pickup::{Pickup, PickupKind};
+use crate::game::portal::PortalPair;
+use crate::game::power_up::PowerUp;
+use crate::game::seed::normalize_seed_code;
+use crate::game::snake::{BodyElement, BodyPartKind, Direction, Snake, TailRule};
+use crate::game::{field_size, MAX_FOOD_COUNT, MAX_GAME_SIZE, MAX_PORTAL_PAIRS, MIN_GAME_SIZE};
+use crate::point::Point;
+use crate::size::Size;
+use std::fmt;
+use std::time::Duration;
+
+pub const SAVE_FILE: &str = "save.cfg";
+
+// keys added later are optional, a save without them gets what a game from before had, so
+// only bump when an existing key changes its meaning, older saves are then refused instead
+// of misread
+const SAVE_VERSION: u32 = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum SaveError {
+    Config(ConfigError),
+    UnsupportedVersion(u32),
+}
+
+/// Everything needed to continue a game exactly where it was left.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub seed: Option<String>,
+    pub game_size: Size,
+    /// Body parts from the head to the tail.
+    pub snake: Vec<BodyElement>,
+    pub direction: Direction,
+    pub next_direction: Direction,
+    /// Parts the snake is still to grow by.
+    pub growth: usize,
+    pub tail_rule: TailRule,
+    /// Rules the game was started with, the settings may have changed since.
+    pub rules: GameRules,
+    pub pickups: Vec<Pickup>,
+    pub portals: Vec<PortalPair>,
+    /// Free cells in the order pickups are placed from, empty when not known.
+    pub free_cells: Vec<Point>,
+    /// Power-ups in effect with the moves they have left.
+    pub power_ups: Vec<(PowerUp, u32)>,
+    pub score: u32,
+    pub cherries_eaten: u32,
+    pub level: u32,
+    pub ticks: u64,
+    pub play_time: Duration,
+    pub rng_state: u64,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Config(error) => error.fmt(f),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save version {}", version)
+            }
+        }
+    }
+}
+
+impl From<ConfigError> for SaveError {
+    fn from(error: ConfigError) -> Self {
+        SaveError::Config(error)
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Right => "right",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Up => "up",
+    }
+}
+
+fn parse_direction(text: &str) -> Option<Direction> {
+    match text {
+        "right" => Some(Direction::Right),
+        "down" => Some(Direction::Down),
+        "left" => Some(Direction::Left),
+        "up" => Some(Direction::Up),
+        _ => None,
+    }
+}
+
+fn kind_name(kind: BodyPartKind) -> &'static str {
+    match kind {
+        BodyPartKind::Head => "head",
+        BodyPartKind::Middle => "middle",
+        BodyPartKind::Tail => "tail",
+    }
+}
+
+fn parse_kind(text: &str) -> Option<BodyPartKind> {
+    match text {
+        "head" => Some(BodyPartKind::Head),
+        "middle" => Some(BodyPartKind::Middle),
+        "tail" => Some(BodyPartKind::Tail),
+        _ => None,
+    }
+}
+
+fn pickup_name(kind: PickupKind) -> &'static str {
+    match kind {
+        PickupKind::Cherry => "cherry",
+        PickupKind::Apple => "apple",
+        PickupKind::PowerUp(power_up) => power_up.key(),
+        PickupKind::Poison => "poison",
+        PickupKind::Bomb => "bomb",
+        PickupKind::Spikes(_) => "spikes",
+    }
+}
+
+fn parse_pickup(text: &str) -> Option<PickupKind> {
+    match text {
+        "cherry" => Some(PickupKind::Cherry),
+        "apple" => Some(PickupKind::Apple),
+        "poison" => Some(PickupKind::Poison),
+        "bomb" => Some(PickupKind::Bomb),
+        _ => PowerUp::from_key(text).map(PickupKind::PowerUp),
+    }
+}
+
+fn pickup_words(pickup: &Pickup) -> String {
+    let mut words = vec![
+        pickup_name(pickup.pickup_kind).to_string(),
+        pickup.position.x.to_string(),
+        pickup.position.y.to_string(),
+    ];
+    // the spikes go on the way they were rolling
+    if let PickupKind::Spikes(direction) = pickup.pickup_kind {
+        words.push(direction_name(direction).to_string());
+    }
+    if let Some(lifetime) = pickup.lifetime {
+        words.push(format!("age {} of {}", pickup.age, lifetime));
+    }
+    if pickup.wanders {
+        words.push("wandering".to_string());
+    }
+
+    words.join(" ")
+}
+
+/// Parses the words of `pickup_words`, the lifetime and the wandering may be left out.
+fn parse_pickup_words(words: &[&str], field_size: Size) -> Option<Pickup> {
+    let (pickup_kind, x, y, mut rest) = match words {
+        ["spikes", x, y, direction, rest @ ..] => {
+            (PickupKind::Spikes(parse_direction(direction)?), x, y, rest)
+        }
+        [kind, x, y, rest @ ..] => (parse_pickup(kind)?, x, y, rest),
+        _ => return None,
+    };
+    let position = parse_point(x, y).filter(|point| inside(*point, field_size))?;
+    let mut pickup = Pickup::new(pickup_kind, position);
+
+    if let ["age", age, "of", lifetime, others @ ..] = rest {
+        pickup.age = age.parse().ok()?;
+        pickup.lifetime = Some(lifetime.parse().ok()?);
+        rest = others;
+    }
+    match rest {
+        [] => (),
+        ["wandering"] => pickup.wanders = true,
+        _ => return None,
+    }
+
+    Some(pickup)
+}
+
+fn parse_point(x: &str, y: &str) -> Option<Point> {
+    Some((x.parse::<i32>().ok()?, y.parse::<i32>().ok()?).into())
+}
+
+/// Parses comma-separated items made of space-separated words, a missing key is an empty list.
+pub fn parse_list<T, F>(config: &Config, key: &str, parse_item: F) -> Result<Vec<T>, SaveError>
+where
+    F: Fn(&[&str]) -> Option<T>,
+{
+    let text: String = config.get(key)?.unwrap_or_default();
+
+    text.split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| {
+            let words: Vec<&str> = item.split_whitespace().collect();
+            parse_item(&words).ok_or_else(|| config.invalid_value(key).into())
+        })
+        .collect()
+}
+
+fn required<T: std::str::FromStr>(config: &Config, key: &str) -> Result<T, SaveError> {
+    Ok(config.get(key)?.ok_or_else(|| config.invalid_value(key))?)
+}
+
+fn inside(point: Point, size: Size) -> bool {
+    point.x >= 0 && point.y >= 0 && point.x < size.width as i32 && point.y < size.height as i32
+}
+
+impl Snapshot {
+    /// Writes the snapshot in the `key = value` format of the settings.
+    pub fn to_text(&self) -> String {
+        let snake: Vec<String> = self
+            .snake
+            .iter()
+            .map(|(point, kind, direction)| {
+                format!(
+                    "{} {} {} {}",
+                    point.x,
+                    point.y,
+                    kind_name(*kind),
+                    direction_name(*direction)
+                )
+            })
+            .collect();
+        let pickups: Vec<String> = self.pickups.iter().map(pickup_words).collect();
+        let portals: Vec<String> = self
+            .portals
+            .iter()
+            .map(
+                |PortalPair {
+                     ends: [first, second],
+                 }| {
+                    format!("{} {} {} {}", first.x, first.y, second.x, second.y)
+                },
+            )
+            .collect();
+        let free_cells: Vec<String> = self
+            .free_cells
+            .iter()
+            .map(|cell| format!("{} {}", cell.x, cell.y))
+            .collect();
+        let power_ups: Vec<String> = self
+            .power_ups
+            .iter()
+            .map(|(power_up, moves)| format!("{} {}", power_up.key(), moves))
+            .collect();
+
+        let lines = [
+            "# Saved snake game, overwritten on the next save".to_string(),
+            format!("version = {}", SAVE_VERSION),
+            format!("mode = {}", self.mode.key()),
+            format!("difficulty = {}", self.difficulty.key()),
+            format!("seed = {}", self.seed.as_deref().unwrap_or_default()),
+            format!("width = {}", self.game_size.width),
+            format!("height = {}", self.game_size.height),
+            format!("score = {}", self.score),
+            format!("cherries_eaten = {}", self.cherries_eaten),
+            format!("level = {}", self.level),
+            format!("ticks = {}", self.ticks),
+            format!("play_time_ms = {}", self.play_time.as_millis()),
+            format!("rng = {}", self.rng_state),
+            format!("direction = {}", direction_name(self.direction)),
+            format!("next_direction = {}", direction_name(self.next_direction)),
+            format!("growth = {}", self.growth),
+            format!("tail_rule = {}", self.tail_rule.key()),
+            format!("food_count = {}", self.rules.food_count),
+            format!("power_up_chance = {}", self.rules.power_up_chance),
+            format!("hazard_chance = {}", self.rules.hazard_chance),
+            format!(
+                "cherry_lifetime = {}",
+                self.rules.cherry_lifetime.unwrap_or(0)
+            ),
+            format!("wandering_cherries = {}", self.rules.wandering_cherries),
+            format!("portal_pairs = {}", self.rules.portal_pairs),
+            format!(
+                "time_limit_ms = {}",
+                self.rules.time_limit.unwrap_or_default().as_millis()
+            ),
+            format!("grow_every = {}", self.rules.grow_every.unwrap_or(0)),
+            format!("snake = {}", snake.join(", ")),
+            format!("pickups = {}", pickups.join(", ")),
+            format!("portals = {}", portals.join(", ")),
+            format!("power_ups = {}", power_ups.join(", ")),
+            format!("free_cells = {}", free_cells.join(", ")),
+        ];
+
+        lines.join("\n") + "\n"
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        let config = Config::parse(text)?;
+
+        let version: u32 = required(&config, "version")?;
+        if version != SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+
+        let mode_key: String = required(&config, "mode")?;
+        let mode = GameMode::from_key(&mode_key).ok_or_else(|| config.invalid_value("mode"))?;
+        let difficulty_key: String = required(&config, "difficulty")?;
+        let difficulty = Difficulty::from_key(&difficulty_key)
+            .ok_or_else(|| config.invalid_value("difficulty"))?;
+        // an empty seed is a random game
+        let seed = match config.get::<String>("seed")?.unwrap_or_default().as_str() {
+            "" => None,
+            code => Some(normalize_seed_code(code).ok_or_else(|| config.invalid_value("seed"))?),
+        };
+
+        // the board has to fit the snake and the screen as in the settings
+        let side = |key, min, max| -> Result<u32, SaveError> {
+            let side: u32 = required(&config, key)?;
+            match (min..=max).contains(&side) {
+                true => Ok(side),
+                false => Err(config.invalid_value(key).into()),
+            }
+        };
+        let game_size: Size = (
+            side("width", MIN_GAME_SIZE.0, MAX_GAME_SIZE.0)?,
+            side("height", MIN_GAME_SIZE.1, MAX_GAME_SIZE.1)?,
+        )
+            .into();
+        let field_size = field_size(game_size);
+
+        let parse_named_direction = |key| -> Result<Direction, SaveError> {
+            let name: String = required(&config, key)?;
+            Ok(parse_direction(&name).ok_or_else(|| config.invalid_value(key))?)
+        };
+        let direction = parse_named_direction("direction")?;
+        let next_direction = parse_named_direction("next_direction")?;
+
+        // saves from before the rules were kept have the ones of the mode by default, written
+        // the way the settings are, with no lifetime, time limit or growth as zero
+        let default_rules = GameRules::default().for_mode(mode);
+        let tail_rule_key: String = config
+            .get("tail_rule")?
+            .unwrap_or_else(|| TailRule::default().key().to_string());
+        let tail_rule =
+            TailRule::from_key(&tail_rule_key).ok_or_else(|| config.invalid_value("tail_rule"))?;
+        let chance = |key, default| -> Result<f64, SaveError> {
+            let chance: f64 = config.get(key)?.unwrap_or(default);
+            match (0.0..=1.0).contains(&chance) {
+                true => Ok(chance),
+                false => Err(config.invalid_value(key).into()),
+            }
+        };
+        let cherry_lifetime: u32 = config
+            .get("cherry_lifetime")?
+            .unwrap_or_else(|| default_rules.cherry_lifetime.unwrap_or(0));
+        let time_limit_ms: u64 = config.get("time_limit_ms")?.unwrap_or_else(|| {
+            default_rules
+                .time_limit
+                .map_or(0, |time_limit| time_limit.as_millis() as u64)
+        });
+        let at_most = |key, default, max| -> Result<u32, SaveError> {
+            let value: u32 = config.get(key)?.unwrap_or(default);
+            match value <= max {
+                true => Ok(value),
+                false => Err(config.invalid_value(key).into()),
+            }
+        };
+        let grow_every: u32 = config
+            .get("grow_every")?
+            .unwrap_or_else(|| default_rules.grow_every.unwrap_or(0));
+        let rules = GameRules {
+            food_count: at_most("food_count", default_rules.food_count, MAX_FOOD_COUNT)?,
+            power_up_chance: chance("power_up_chance", default_rules.power_up_chance)?,
+            hazard_chance: chance("hazard_chance", default_rules.hazard_chance)?,
+            cherry_lifetime: Some(cherry_lifetime).filter(|lifetime| *lifetime > 0),
+            wandering_cherries: config
+                .get("wandering_cherries")?
+                .unwrap_or(default_rules.wandering_cherries),
+            portal_pairs: at_most("portal_pairs", default_rules.portal_pairs, MAX_PORTAL_PAIRS)?,
+            time_limit: Some(Duration::from_millis(time_limit_ms))
+                .filter(|time_limit| *time_limit > Duration::from_secs(0)),
+            grow_every: Some(grow_every).filter(|grow_every| *grow_every > 0),
+        };
+
+        let snake = parse_list(&config, "snake", |words| match words {
+            [x, y, kind, direction] => Some((
+                parse_point(x, y).filter(|point| inside(*point, field_size))?,
+                parse_kind(kind)?,
+                parse_direction(direction)?,
+            )),
+            _ => None,
+        })?;
+        let mut restored = Snake::from_parts(snake.clone(), direction, next_direction, field_size)
+            .ok_or_else(|| config.invalid_value("snake"))?;
+
+        // saves from before the portals have none
+        let portals = parse_list(&config, "portals", |words| match words {
+            [x1, y1, x2, y2] => Some(PortalPair::new(
+                parse_point(x1, y1).filter(|point| inside(*point, field_size))?,
+                parse_point(x2, y2).filter(|point| inside(*point, field_size))?,
+            )),
+            _ => None,
+        })?;
+        if !restored.set_portals(portals.clone()) {
+            return Err(config.invalid_value("portals").into());
+        }
+
+        // saves from before keep the order the cells come in, and only place pickups differently
+        let free_cells = parse_list(&config, "free_cells", |words| match words {
+            [x, y] => parse_point(x, y),
+            _ => None,
+        })?;
+        if !free_cells.is_empty() && !restored.set_free_order(&free_cells) {
+            return Err(config.invalid_value("free_cells").into());
+        }
+
+        let pickups = parse_list(&config, "pickups", |words| {
+            parse_pickup_words(words, field_size)
+        })?;
+        // only a surviving snake goes without food, and pickups lie on free cells of their own
+        let overlapping = pickups.iter().enumerate().any(|(index, pickup)| {
+            restored.occupancy().is_occupied(pickup.position)
+                || pickups[..index]
+                    .iter()
+                    .any(|other| other.position == pickup.position)
+        });
+        if (pickups.is_empty() && rules.food_count > 0) || overlapping {
+            return Err(config.invalid_value("pickups").into());
+        }
+
+        // saves from before the power-ups have none in effect
+        let power_ups = parse_list(&config, "power_ups", |words| match words {
+            [power_up, moves] => Some((PowerUp::from_key(power_up)?, moves.parse().ok()?)),
+            _ => None,
+        })?;
+
+        Ok(Snapshot {
+            mode,
+            difficulty,
+            seed,
+            game_size,
+            snake,
+            direction,
+            next_direction,
+            growth: config.get("growth")?.unwrap_or(0),
+            tail_rule,
+            rules,
+            pickups,
+            portals,
+            free_cells,
+            power_ups,
+            score: required(&config, "score")?,
+            cherries_eaten: required(&config, "cherries_eaten")?,
+            level: required(&config, "level")?,
+            ticks: required(&config, "ticks")?,
+            play_time: Duration::from_millis(required(&config, "play_time_ms")?),
+            rng_state: required(&config, "rng")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        let mut snake = Snake::new((4, 2), 4, field_size((12, 8).into()));
+        let portals = vec![PortalPair::new((7, 3), (1, 0))];
+        snake.set_portals(portals.clone());
+
+        Snapshot {
+            mode: GameMode::Classic,
+            difficulty: Difficulty::Hard,
+            seed: Some("APPLE7".to_string()),
+            game_size: (12, 8).into(),
+            snake: snake.parts().cloned().collect(),
+            direction: Direction::Right,
+            next_direction: Direction::Up,
+            growth: 1,
+            tail_rule: TailRule::Permissive,
+            rules: GameRules {
+                food_count: 2,
+                cherry_lifetime: Some(30),
+                ..GameRules::default().for_mode(GameMode::Classic)
+            },
+            pickups: vec![
+                Pickup::new(PickupKind::Cherry, (6, 3)),
+                Pickup {
+                    age: 4,
+                    ..Pickup::new(PickupKind::PowerUp(PowerUp::Ghost), (8, 1))
+                        .with_lifetime(Some(40))
+                },
+                Pickup::new(PickupKind::Spikes(Direction::Down), (0, 4)),
+                Pickup::new(PickupKind::Cherry, (2, 4)).wandering(true),
+            ],
+            free_cells: snake.occupancy().free_cells().to_vec(),
+            portals,
+            power_ups: vec![(PowerUp::DoublePoints, 12)],
+            score: 7,
+            cherries_eaten: 7,
+            level: 2,
+            ticks: 123,
+            play_time: Duration::from_millis(45_678),
+            rng_state: u64::MAX,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let snapshot = snapshot();
+        assert_eq!(Snapshot::parse(&snapshot.to_text()), Ok(snapshot));
+    }
+
+    #[test]
+    fn test_without_seed() {
+        let snapshot = Snapshot {
+            seed: None,
+            ..snapshot()
+        };
+        let text = snapshot.to_text();
+
+        assert!(text.contains("seed = \n"));
+        assert_eq!(Snapshot::parse(&text), Ok(snapshot.clone()));
+        // saves from before the seed codes were random
+        assert_eq!(
+            Snapshot::parse(&text.replace("seed = \n", "")),
+            Ok(snapshot)
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let text = snapshot().to_text();
+
+        assert!(text.contains("version = 3\n"));
+        assert!(text.contains(
+            "snake = 4 2 head right, 3 2 middle right, 2 2 middle right, 1 2 tail right\n"
+        ));
+        assert!(text.contains(
+            "pickups = cherry 6 3, ghost 8 1 age 4 of 40, spikes 0 4 down, cherry 2 4 wandering\n"
+        ));
+        assert!(text.contains("portals = 7 3 1 0\n"));
+        assert!(text.contains("growth = 1\ntail_rule = permissive\nfood_count = 2\n"));
+        assert!(text.contains("cherry_lifetime = 30\n"));
+        assert!(text.contains("time_limit_ms = 0\ngrow_every = 0\n"));
+        assert!(text.contains("power_ups = double_points 12\n"));
+    }
+
+    #[test]
+    fn test_without_portals() {
+        let snapshot = Snapshot {
+            portals: Vec::new(),
+            free_cells: Vec::new(),
+            ..snapshot()
+        };
+        let text = snapshot.to_text();
+
+        assert!(text.contains("portals = \n"));
+        assert_eq!(
+            Snapshot::parse(&text.replace("portals = \n", "")),
+            Ok(snapshot)
+        );
+    }
+
+    #[test]
+    fn test_without_free_cells() {
+        let snapshot = Snapshot {
+            free_cells: Vec::new(),
+            ..snapshot()
+        };
+        let text = snapshot.to_text();
+
+        assert!(text.contains("free_cells = \n"));
+        assert_eq!(
+            Snapshot::parse(&text.replace("free_cells = \n", "")),
+            Ok(snapshot)
+        );
+    }
+
+    #[test]
+    fn test_without_power_ups() {
+        let snapshot = Snapshot {
+            power_ups: Vec::new(),
+            ..snapshot()
+        };
+        let text = snapshot.to_text();
+
+        assert!(text.contains("power_ups = \n"));
+        assert_eq!(
+            Snapshot::parse(&text.replace("power_ups = \n", "")),
+            Ok(snapshot)
+        );
+    }
+
+    #[test]
+    fn test_survival_without_pickups() {
+        let snapshot = Snapshot {
+            mode: GameMode::Survival,
+            rules: GameRules::default().for_mode(GameMode::Survival),
+            pickups: Vec::new(),
+            ..snapshot()
+        };
+        assert_eq!(Snapshot::parse(&snapshot.to_text()), Ok(snapshot));
+    }
+
+    #[test]
+    fn test_without_rules() {
+        let snapshot = Snapshot {
+            mode: GameMode::TimeAttack,
+            growth: 0,
+            tail_rule: TailRule::default(),
+            rules: GameRules::default().for_mode(GameMode::TimeAttack),
+            ..snapshot()
+        };
+        let text = snapshot.to_text();
+
+        assert!(text.contains("time_limit_ms = 120000\n"));
+        // saves from before the rules were kept play by the defaults of their mode
+        let keys = [
+            "growth",
+            "tail_rule",
+            "food_count",
+            "power_up_chance",
+            "hazard_chance",
+            "cherry_lifetime",
+            "wandering_cherries",
+            "portal_pairs",
+            "time_limit_ms",
+            "grow_every",
+        ];
+        let old_text: String = text
+            .lines()
+            .filter(|line| {
+                !keys
+                    .iter()
+                    .any(|key| line.starts_with(&format!("{} =", key)))
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(Snapshot::parse(&old_text), Ok(snapshot));
+    }
+
+    #[test]
+    fn test_board_size_limits() {
+        // a truncated board would leave no field to play on
+        for &game_size in &[(1, 8), (12, 2), (0, 0), (12, 100_000)] {
+            let snapshot = Snapshot {
+                game_size: game_size.into(),
+                ..snapshot()
+            };
+            assert!(
+                Snapshot::parse(&snapshot.to_text()).is_err(),
+                "{:?}",
+                game_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let text = snapshot().to_text().replace("version = 3", "version = 2");
+        assert_eq!(
+            Snapshot::parse(&text),
+            Err(SaveError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn test_invalid_saves() {
+        let text = snapshot().to_text();
+        let invalid = |from: &str, to: &str| Snapshot::parse(&text.replacen(from, to, 1)).is_err();
+
+        assert!(invalid("score = 7", "score = lots"));
+        assert!(invalid("score = 7", ""));
+        assert!(invalid("mode = classic", "mode = chess"));
+        assert!(invalid("difficulty = hard", "difficulty = insane"));
+        assert!(invalid("seed = APPLE7", "seed = APPLE 7"));
+        assert!(invalid("direction = right", "direction = north"));
+        // the snake outside of the field
+        assert!(invalid("4 2 head", "40 2 head"));
+        // a snake without a tail
+        assert!(invalid(", 1 2 tail right", ""));
+        assert!(invalid("cherry 6 3", "banana 6 3"));
+        assert!(invalid(
+            "cherry 6 3, ghost 8 1 age 4 of 40, spikes 0 4 down, cherry 2 4 wandering",
+            ""
+        ));
+        assert!(invalid("spikes 0 4 down", "spikes 0 4"));
+        assert!(invalid("age 4 of 40", "age 4"));
+        assert!(invalid("2 4 wandering", "2 4 wandering twice"));
+        // portals on the snake, next to each other or outside of the field
+        assert!(invalid("portals = 7 3 1 0", "portals = 7 3 3 2"));
+        assert!(invalid("portals = 7 3 1 0", "portals = 7 3 1 0, 6 3 8 0"));
+        assert!(invalid("portals = 7 3 1 0", "portals = 7 3 1 9"));
+        assert!(invalid("portals = 7 3 1 0", "portals = 7 3"));
+        assert!(invalid("double_points 12", "double_points soon"));
+        // pickups on the snake, on a portal or on each other
+        assert!(invalid("cherry 6 3", "cherry 3 2"));
+        assert!(invalid("cherry 6 3", "cherry 7 3"));
+        assert!(invalid("cherry 2 4", "cherry 6 3"));
+        assert!(invalid("tail_rule = permissive", "tail_rule = never"));
+        assert!(invalid("hazard_chance = 0.1", "hazard_chance = 1.5"));
+        assert!(invalid("growth = 1", "growth = -1"));
+        assert!(invalid("food_count = 2", "food_count = 1000"));
+        assert!(invalid("portal_pairs = 1", "portal_pairs = 50"));
+        // free cells which are taken, missing or given twice
+        assert!(invalid("free_cells = 0 0", "free_cells = 1 0"));
+        assert!(invalid("free_cells = 0 0, ", "free_cells = "));
+        assert!(invalid("free_cells = 0 0", "free_cells = 0 0, 0 0"));
+    }
+}
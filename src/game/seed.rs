@@ -0,0 +1,109 @@
+use crate::game::rng::GameRng;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest seed code which can be entered.
+pub const MAX_SEED_CODE_LENGTH: usize = 12;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Uppercases the code and checks that it is made of letters and digits only.
+pub fn normalize_seed_code(code: &str) -> Option<String> {
+    let code = code.trim().to_ascii_uppercase();
+    let valid = !code.is_empty()
+        && code.len() <= MAX_SEED_CODE_LENGTH
+        && code
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric());
+
+    match valid {
+        true => Some(code),
+        false => None,
+    }
+}
+
+/// Random number generator of a game played with the code, any code gives the same pickups on
+/// any computer.
+pub fn seeded_rng(code: &str) -> GameRng {
+    // FNV-1a, spread further by the generator itself
+    let state = code
+        .to_ascii_uppercase()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+
+    GameRng::from_state(state)
+}
+
+/// Code of the daily challenge, the UTC date as `YYYYMMDD`.
+pub fn daily_seed_code(since_epoch: Duration) -> String {
+    let days = (since_epoch.as_secs() / SECONDS_PER_DAY) as i64;
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}{:02}{:02}", year, month, day)
+}
+
+pub fn today_seed_code() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    daily_seed_code(since_epoch)
+}
+
+/// Date of the day number since 1970-01-01, after Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = match shifted_month < 10 {
+        true => shifted_month + 3,
+        false => shifted_month - 9,
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_normalize_seed_code() {
+        assert_eq!(normalize_seed_code(" apple7 "), Some("APPLE7".to_string()));
+        assert_eq!(normalize_seed_code(""), None);
+        assert_eq!(normalize_seed_code("two words"), None);
+        assert_eq!(normalize_seed_code("ABCDEFGHIJKLM"), None);
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+        assert_eq!(
+            seeded_rng("apple").next_u64(),
+            seeded_rng("APPLE").next_u64()
+        );
+        assert_ne!(
+            seeded_rng("APPLE").next_u64(),
+            seeded_rng("APPLF").next_u64()
+        );
+    }
+
+    #[test]
+    fn test_daily_seed_code() {
+        let day = Duration::from_secs(SECONDS_PER_DAY);
+        assert_eq!(daily_seed_code(Duration::from_secs(0)), "19700101");
+        assert_eq!(daily_seed_code(day * 59), "19700301");
+        // leap days
+        assert_eq!(daily_seed_code(day * 11_016), "20000229");
+        assert_eq!(
+            daily_seed_code(day * 20_745 - Duration::from_secs(1)),
+            "20261018"
+        );
+        assert_eq!(daily_seed_code(day * 20_745), "20261019");
+    }
+}
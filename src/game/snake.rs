@@ -1,12 +1,19 @@
+use crate::game::occupancy::OccupancyGrid;
+use crate::game::palette;
 use crate::game::pickup::Pickup;
+use crate::game::portal::PortalPair;
 use crate::game::snake_sprite::SpriteData;
 use crate::point::Point;
 use crate::size::Size;
+use crate::sprite_batch::Rotation;
 use crate::sprite_renderer::GenericContext;
-use opengl_graphics::Texture;
-use std::collections::LinkedList;
+use graphics::types::Color;
+use std::collections::{HashMap, VecDeque};
 
-#[derive(Copy, Clone, PartialEq)]
+// the shrink power-up leaves the head and the tail
+const MIN_LENGTH: usize = 2;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Direction {
     Right,
     Down,
@@ -14,43 +21,109 @@ pub enum Direction {
     Up,
 }
 
-enum BodyPartKind {
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BodyPartKind {
     Head,
     Middle,
     Tail,
 }
 
-type BodyElement = (Point, BodyPartKind, Direction);
+impl Direction {
+    /// Change of the coordinates for a move in the direction.
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+}
+
+pub type BodyElement = (Point, BodyPartKind, Direction);
 
 pub struct Snake {
-    body: LinkedList<BodyElement>,
+    // head first, a move pushes at the front and pops at the back
+    body: VecDeque<BodyElement>,
+    // cells of the body, updated on every move
+    occupancy: OccupancyGrid,
+    // parts beyond the first one in the cells where a ghost snake went over itself
+    overlaps: HashMap<Point, u32>,
     direction: Direction,
     next_direction: Direction,
     field_size: Size,
+    color: Color,
+    tail_rule: TailRule,
+    ghost: bool,
+    // the body is never on a portal, their cells are kept occupied
+    portals: Vec<PortalPair>,
+    // parts to be added on the next moves without a pickup
+    growth: usize,
 }
 
 pub enum NewCell {
     Empty,
-    Pickup,
+    /// The head went onto the pickup with the index, the snake grew.
+    Pickup(usize),
 }
 
+/// What the head ran into, with the cell of the hit. Border cells lie just outside the field.
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Collision {
-    Body,
-    Border,
+    Body(Point),
+    Border(Point),
+    /// A bomb or spikes, which the snake doesn't check for itself.
+    Hazard(Point),
+}
+
+/// Whether the head may go into the cell of the tail.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum TailRule {
+    /// Never, the tail is body like any other part.
+    Strict,
+    /// Only when the tail moves out of the way, that is when the snake doesn't grow on the move.
+    #[default]
+    Classic,
+    /// Always, on a growing move the tail moves on anyway and the snake doesn't grow.
+    Permissive,
 }
 
-fn get_rotated_texture_variant<'a>(
-    textures: &'a [Texture; 4],
-    direction: &Direction,
-) -> &'a Texture {
-    use Direction::*;
-    let [right, down, left, up] = textures;
+impl TailRule {
+    /// Name used in the settings.
+    pub fn key(self) -> &'static str {
+        match self {
+            TailRule::Strict => "strict",
+            TailRule::Classic => "classic",
+            TailRule::Permissive => "permissive",
+        }
+    }
 
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "strict" => Some(TailRule::Strict),
+            "classic" => Some(TailRule::Classic),
+            "permissive" => Some(TailRule::Permissive),
+            _ => None,
+        }
+    }
+}
+
+/// The sprites face right, other directions are drawn rotated.
+fn get_rotation(direction: &Direction) -> Rotation {
     match direction {
-        Right => right,
-        Down => down,
-        Left => left,
-        Up => up,
+        Direction::Right => Rotation::None,
+        Direction::Down => Rotation::Quarter,
+        Direction::Left => Rotation::Half,
+        Direction::Up => Rotation::ThreeQuarters,
     }
 }
 
@@ -58,7 +131,7 @@ impl Snake {
     pub fn new<P: Into<Point>, S: Into<Size>>(head_start: P, length: u32, field_size: S) -> Self {
         let head_start: Point = head_start.into();
 
-        let mut body = LinkedList::new();
+        let mut body = VecDeque::with_capacity(length as usize);
         body.push_back((head_start, BodyPartKind::Head, Direction::Right));
 
         for i in 1..length - 1 {
@@ -75,51 +148,237 @@ impl Snake {
             Direction::Right,
         ));
 
-        Snake {
-            direction: Direction::Right,
-            next_direction: Direction::Right,
+        Snake::with_body(body, Direction::Right, Direction::Right, field_size.into())
+    }
+
+    fn with_body(
+        body: VecDeque<BodyElement>,
+        direction: Direction,
+        next_direction: Direction,
+        field_size: Size,
+    ) -> Self {
+        let mut snake = Snake {
             body,
-            field_size: field_size.into(),
+            occupancy: OccupancyGrid::new(field_size),
+            overlaps: HashMap::new(),
+            direction,
+            next_direction,
+            field_size,
+            color: palette::WHITE,
+            tail_rule: TailRule::default(),
+            ghost: false,
+            portals: Vec::new(),
+            growth: 0,
+        };
+
+        for index in 0..snake.body.len() {
+            let (point, _, _) = snake.body[index];
+            snake.occupy_cell(point);
         }
+        snake
+    }
+
+    /// Puts back a snake from its parts, head first, as returned by `parts`.
+    ///
+    /// Returns `None` when the parts don't make up a snake or lie outside of the field. Parts may
+    /// overlap, as they do after the snake went over itself as a ghost.
+    pub fn from_parts<S: Into<Size>>(
+        parts: Vec<BodyElement>,
+        direction: Direction,
+        next_direction: Direction,
+        field_size: S,
+    ) -> Option<Self> {
+        let last = parts.len().checked_sub(1)?;
+        let valid_kinds = parts.iter().enumerate().all(|(index, (_, kind, _))| {
+            *kind
+                == match index {
+                    0 => BodyPartKind::Head,
+                    index if index == last => BodyPartKind::Tail,
+                    _ => BodyPartKind::Middle,
+                }
+        });
+
+        if last == 0 || !valid_kinds {
+            return None;
+        }
+
+        let field_size: Size = field_size.into();
+        let inside = parts.iter().all(|(point, _, _)| {
+            point.x >= 0
+                && point.y >= 0
+                && (point.x as u32) < field_size.width
+                && (point.y as u32) < field_size.height
+        });
+        if !inside {
+            return None;
+        }
+
+        Some(Snake::with_body(
+            parts.into_iter().collect(),
+            direction,
+            next_direction,
+            field_size,
+        ))
     }
 
     pub fn render<C>(&self, context: &mut C, sprites: &SpriteData)
     where
         C: GenericContext,
     {
-        for (point, kind, rotation) in &self.body {
-            let texture = match kind {
-                BodyPartKind::Head => get_rotated_texture_variant(&sprites.snake_head, rotation),
-                BodyPartKind::Middle => get_rotated_texture_variant(&sprites.snake_body, rotation),
-                BodyPartKind::Tail => get_rotated_texture_variant(&sprites.snake_tail, rotation),
+        self.render_tinted(context, sprites, self.color);
+    }
+
+    /// Renders the snake with a color other than its own.
+    pub fn render_tinted<C>(&self, context: &mut C, sprites: &SpriteData, color: Color)
+    where
+        C: GenericContext,
+    {
+        self.render_parts(context, sprites, color, self.body.len());
+    }
+
+    /// Renders only the first `count` parts of the body, starting from the head.
+    pub fn render_parts<C>(&self, context: &mut C, sprites: &SpriteData, color: Color, count: usize)
+    where
+        C: GenericContext,
+    {
+        for (point, kind, rotation) in self.body.iter().take(count) {
+            let sprite = match kind {
+                BodyPartKind::Head => &sprites.snake_head,
+                BodyPartKind::Middle => &sprites.snake_body,
+                BodyPartKind::Tail => &sprites.snake_tail,
             };
 
-            context.draw_sprite(*point, texture);
+            context.draw_sprite_rotated(*point, sprite, get_rotation(rotation), color);
         }
     }
 
+    pub fn head(&self) -> Point {
+        let (head_position, _, _) = self.body.front().expect("Body is empty.");
+        *head_position
+    }
+
+    pub fn length(&self) -> usize {
+        self.body.len()
+    }
+
     pub fn direction(&self) -> Direction {
         self.direction
     }
 
-    pub fn get_occupied_cells(&self) -> Vec<Point> {
-        self.body.iter().map(|(point, _, _)| *point).collect()
+    pub fn next_direction(&self) -> Direction {
+        self.next_direction
+    }
+
+    /// Body parts from the head to the tail.
+    pub fn parts(&self) -> impl Iterator<Item = &BodyElement> {
+        self.body.iter()
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    pub fn tail_rule(&self) -> TailRule {
+        self.tail_rule
+    }
+
+    pub fn set_tail_rule(&mut self, tail_rule: TailRule) {
+        self.tail_rule = tail_rule;
+    }
+
+    /// Lets the snake pass through its own body, the borders still stop it.
+    pub fn set_ghost(&mut self, ghost: bool) {
+        self.ghost = ghost;
+    }
+
+    /// Puts portals on the field in place of the ones there were.
+    ///
+    /// Returns `false` and keeps the old portals when the new ones lie outside of the field, on
+    /// the body, or next to another end, where the snake would come out into a portal.
+    pub fn set_portals(&mut self, portals: Vec<PortalPair>) -> bool {
+        for portal in &self.portals {
+            for end in &portal.ends {
+                self.occupancy.free(*end);
+            }
+        }
+
+        let ends: Vec<Point> = portals.iter().flat_map(|portal| portal.ends).collect();
+        let valid = ends.iter().enumerate().all(|(index, end)| {
+            !self.occupancy.is_occupied(*end)
+                && ends[index + 1..]
+                    .iter()
+                    .all(|other| (end.x - other.x).abs() + (end.y - other.y).abs() > 1)
+        });
+        if valid {
+            self.portals = portals;
+        }
+
+        for portal in &self.portals {
+            for end in &portal.ends {
+                self.occupancy.occupy(*end);
+            }
+        }
+        valid
+    }
+
+    /// Puts the free cells of the field in the given order, see `OccupancyGrid::set_free_order`.
+    pub fn set_free_order(&mut self, cells: &[Point]) -> bool {
+        self.occupancy.set_free_order(cells)
+    }
+
+    pub fn portals(&self) -> &[PortalPair] {
+        &self.portals
+    }
+
+    /// Makes the snake grow by a part on the next move, as if it ate a pickup.
+    pub fn grow(&mut self) {
+        self.growth += 1;
+    }
+
+    /// Parts still to be added on the next moves.
+    pub fn growth(&self) -> usize {
+        self.growth
+    }
+
+    /// Cuts up to `segments` parts off the tail, the head and the tail are always left.
+    pub fn shrink(&mut self, segments: usize) {
+        let segments = segments.min(self.body.len().saturating_sub(MIN_LENGTH));
+        for _ in 0..segments {
+            let (tail_position, _, _) = self.body.pop_back().expect("Body is empty.");
+            self.free_cell(tail_position);
+        }
+
+        if segments > 0 {
+            self.update_tail();
+        }
+    }
+
+    /// Cells of the field taken by the body and the portals.
+    pub fn occupancy(&self) -> &OccupancyGrid {
+        &self.occupancy
     }
 
     /// Moves the snake one cell in selected direction.
     ///
     /// # Arguments
     ///
-    /// * `max_x` - Maximum value for X coordinate, the boundary of level.
-    /// * `max_y` - Maximum value for Y coordinate, the boundary of level.
-    pub fn advance(&mut self, cherry_pickup: &Pickup) -> Result<NewCell, Collision> {
+    /// * `pickups` - Pickups on the field, the snake grows when the head goes onto one of them.
+    pub fn advance(&mut self, pickups: &[Pickup]) -> Result<NewCell, Collision> {
         let (head_position, _, _) = self.body.front().expect("Body is empty.");
-        let new_head_position = match self.next_direction {
-            Direction::Left => head_position.offset((-1, 0)),
-            Direction::Right => head_position.offset((1, 0)),
-            Direction::Up => head_position.offset((0, -1)),
-            Direction::Down => head_position.offset((0, 1)),
-        };
+        let mut new_head_position = head_position.offset(self.next_direction.offset());
+
+        // going into a portal comes out past the other end, heading the same way
+        if let Some(exit) = self
+            .portals
+            .iter()
+            .find_map(|portal| portal.other_end(new_head_position))
+        {
+            new_head_position = exit.offset(self.next_direction.offset());
+        }
 
         // check for borders first
         if new_head_position.x < 0
@@ -127,21 +386,32 @@ impl Snake {
             || new_head_position.y < 0
             || new_head_position.y >= self.field_size.height as i32
         {
-            return Err(Collision::Border);
+            return Err(Collision::Border(new_head_position));
         }
 
-        // check for own_body
-        if self
-            .body
+        let picked = pickups
             .iter()
-            .rev()
-            .skip(1)
-            .any(|(part_position, _, _)| *part_position == new_head_position)
-        {
-            return Err(Collision::Body);
-        }
+            .position(|pickup| pickup.position == new_head_position);
+        let mut grows = picked.is_some() || self.growth > 0;
 
-        let picked_cherry = cherry_pickup.position == new_head_position;
+        // check for own body, the tail stays in its cell when the snake grows
+        let (tail_position, _, _) = self.body.back().expect("Body is empty.");
+        if self.occupancy.is_occupied(new_head_position) && !self.ghost {
+            // the tail only clears its cell when no other part is left there
+            let into_tail = new_head_position == *tail_position
+                && !self.overlaps.contains_key(&new_head_position);
+            let tail_allowed = match (into_tail, self.tail_rule) {
+                (false, _) | (true, TailRule::Strict) => false,
+                (true, TailRule::Classic) => !grows,
+                (true, TailRule::Permissive) => true,
+            };
+            if !tail_allowed {
+                return Err(Collision::Body(new_head_position));
+            }
+
+            // the tail moves out of the way, the growth is lost
+            grows = false;
+        }
 
         self.direction = self.next_direction;
 
@@ -149,37 +419,65 @@ impl Snake {
         let (_, body_part_kind, _) = self.body.front_mut().expect("Body is empty.");
         *body_part_kind = BodyPartKind::Middle;
 
+        if !grows {
+            // remove tail
+            let (tail_position, _, _) = self.body.pop_back().expect("Body is empty.");
+            self.free_cell(tail_position);
+        }
+
         // attach new head
         self.body
             .push_front((new_head_position, BodyPartKind::Head, self.direction));
+        self.occupy_cell(new_head_position);
 
-        if !picked_cherry {
-            // remove tail
-            self.body.pop_back();
-
-            // change new back kind
-            let (previous_tail_position, _, _) =
-                *self.body.iter().rev().nth(1).expect("Body is too short.");
-
-            let (tail_position, tail_part_kind, tail_part_direction) =
-                self.body.back_mut().expect("Body is empty.");
-            *tail_part_kind = BodyPartKind::Tail;
-
-            *tail_part_direction = match (
-                tail_position.x - previous_tail_position.x,
-                tail_position.y - previous_tail_position.y,
-            ) {
-                (1, _) => Direction::Left,
-                (-1, _) => Direction::Right,
-                (_, 1) => Direction::Up,
-                (_, -1) => Direction::Down,
-                _ => *tail_part_direction,
-            };
+        match grows {
+            true if picked.is_none() => self.growth -= 1,
+            true => (),
+            false => self.update_tail(),
+        }
+
+        match picked {
+            Some(index) => Ok(NewCell::Pickup(index)),
+            None => Ok(NewCell::Empty),
+        }
+    }
+
+    /// Turns the last part into the tail, facing away from the part before it.
+    fn update_tail(&mut self) {
+        let (previous_tail_position, _, previous_direction) =
+            *self.body.iter().rev().nth(1).expect("Body is too short.");
+
+        let (tail_position, tail_part_kind, tail_part_direction) =
+            self.body.back_mut().expect("Body is empty.");
+        *tail_part_kind = BodyPartKind::Tail;
+
+        *tail_part_direction = match (
+            tail_position.x - previous_tail_position.x,
+            tail_position.y - previous_tail_position.y,
+        ) {
+            (1, 0) => Direction::Left,
+            (-1, 0) => Direction::Right,
+            (0, 1) => Direction::Up,
+            (0, -1) => Direction::Down,
+            // the part before went through a portal, the tail follows the way it went
+            _ => previous_direction,
+        };
+    }
+
+    fn occupy_cell(&mut self, point: Point) {
+        match self.occupancy.is_occupied(point) {
+            true => *self.overlaps.entry(point).or_insert(0) += 1,
+            false => self.occupancy.occupy(point),
         }
+    }
 
-        match picked_cherry {
-            true => Ok(NewCell::Pickup),
-            false => Ok(NewCell::Empty),
+    fn free_cell(&mut self, point: Point) {
+        match self.overlaps.get_mut(&point) {
+            Some(count) if *count > 1 => *count -= 1,
+            Some(_) => {
+                self.overlaps.remove(&point);
+            }
+            None => self.occupancy.free(point),
         }
     }
 
@@ -187,3 +485,339 @@ impl Snake {
         self.next_direction = direction;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::pickup::PickupKind;
+    use std::time::Instant;
+
+    fn far_pickup() -> Pickup {
+        Pickup::new(PickupKind::Cherry, (100, 100))
+    }
+
+    #[test]
+    fn test_border_collision_cell() {
+        let mut snake = Snake::new((4, 0), 5, (6, 4));
+        assert!(snake.advance(&[far_pickup()]).is_ok());
+
+        snake.set_next_direction(Direction::Up);
+        assert_eq!(
+            snake.advance(&[far_pickup()]).err(),
+            Some(Collision::Border((5, -1).into()))
+        );
+    }
+
+    #[test]
+    fn test_body_collision_cell() {
+        let mut snake = Snake::new((4, 1), 5, (8, 8));
+
+        for direction in &[Direction::Down, Direction::Left] {
+            snake.set_next_direction(*direction);
+            assert!(snake.advance(&[far_pickup()]).is_ok());
+        }
+
+        snake.set_next_direction(Direction::Up);
+        assert_eq!(
+            snake.advance(&[far_pickup()]).err(),
+            Some(Collision::Body((3, 1).into()))
+        );
+        assert_eq!(snake.length(), 5);
+    }
+
+    #[test]
+    fn test_reports_the_eaten_pickup() {
+        let mut snake = Snake::new((4, 1), 5, (8, 8));
+        let cherry = Pickup::new(PickupKind::Cherry, (5, 1));
+
+        match snake.advance(&[far_pickup(), cherry]) {
+            Ok(NewCell::Pickup(index)) => assert_eq!(index, 1),
+            _ => panic!("Expected the second pickup to be eaten"),
+        }
+        assert_eq!(snake.length(), 6);
+    }
+
+    /// A snake curled up in a 2x2 square, with its head right under the tail.
+    fn curled_snake(tail_rule: TailRule) -> Snake {
+        let parts = vec![
+            ((0, 1).into(), BodyPartKind::Head, Direction::Left),
+            ((1, 1).into(), BodyPartKind::Middle, Direction::Down),
+            ((1, 0).into(), BodyPartKind::Middle, Direction::Right),
+            ((0, 0).into(), BodyPartKind::Tail, Direction::Right),
+        ];
+        let mut snake = Snake::from_parts(parts, Direction::Left, Direction::Up, (4, 4))
+            .expect("The parts make a snake");
+        snake.set_tail_rule(tail_rule);
+        snake
+    }
+
+    #[test]
+    fn test_tail_rules() {
+        let bitten = Some(Collision::Body((0, 0).into()));
+
+        // the rule, whether the snake grows on the move, and the collision if any
+        let cases = [
+            (TailRule::Strict, false, bitten),
+            (TailRule::Strict, true, bitten),
+            (TailRule::Classic, false, None),
+            (TailRule::Classic, true, bitten),
+            (TailRule::Permissive, false, None),
+            (TailRule::Permissive, true, None),
+        ];
+
+        for &(tail_rule, grows, collision) in &cases {
+            let mut snake = curled_snake(tail_rule);
+            let pickup = match grows {
+                true => Pickup::new(PickupKind::Cherry, (0, 0)),
+                false => far_pickup(),
+            };
+
+            let result = snake.advance(&[pickup]);
+            assert_eq!(result.as_ref().err(), collision.as_ref(), "{:?}", tail_rule);
+
+            // a move into the tail never makes the snake longer or overlap itself
+            assert_eq!(snake.length(), 4);
+            assert_eq!(snake.occupancy().free_count(), 12);
+            for (point, _, _) in snake.parts() {
+                assert!(snake.occupancy().is_occupied(*point));
+            }
+            if collision.is_none() {
+                assert_eq!(snake.head(), (0, 0).into());
+                assert_eq!(snake.parts().last().unwrap().0, (1, 0).into());
+            }
+        }
+    }
+
+    #[test]
+    fn test_tail_rule_keys() {
+        for &tail_rule in &[TailRule::Strict, TailRule::Classic, TailRule::Permissive] {
+            assert_eq!(TailRule::from_key(tail_rule.key()), Some(tail_rule));
+        }
+        assert_eq!(TailRule::from_key("lenient"), None);
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let snake = Snake::new((4, 1), 4, (8, 8));
+        let parts: Vec<BodyElement> = snake.parts().cloned().collect();
+
+        let copy = Snake::from_parts(parts.clone(), Direction::Right, Direction::Down, (8, 8))
+            .expect("Parts of a snake make a snake");
+        assert_eq!(copy.parts().cloned().collect::<Vec<_>>(), parts);
+        assert_eq!(copy.next_direction(), Direction::Down);
+
+        let headless = parts[1..].to_vec();
+        assert!(Snake::from_parts(headless, Direction::Right, Direction::Right, (8, 8)).is_none());
+        assert!(Snake::from_parts(
+            parts[..1].to_vec(),
+            Direction::Right,
+            Direction::Right,
+            (8, 8)
+        )
+        .is_none());
+        assert!(
+            Snake::from_parts(Vec::new(), Direction::Right, Direction::Right, (8, 8)).is_none()
+        );
+
+        // a ghost leaves the snake over itself
+        let mut overlapping = parts.clone();
+        overlapping[1].0 = overlapping[2].0;
+        let overlapping =
+            Snake::from_parts(overlapping, Direction::Right, Direction::Right, (8, 8))
+                .expect("Overlapping parts make a snake");
+        assert_eq!(overlapping.occupancy().free_count(), 61);
+        assert!(Snake::from_parts(parts, Direction::Right, Direction::Right, (4, 8)).is_none());
+    }
+
+    #[test]
+    fn test_occupancy_follows_the_body() {
+        let mut snake = Snake::new((2, 0), 3, (4, 4));
+        assert_eq!(snake.occupancy().free_count(), 13);
+
+        snake.set_next_direction(Direction::Down);
+        assert!(snake.advance(&[far_pickup()]).is_ok());
+        assert!(!snake.occupancy().is_occupied((0, 0).into()));
+        assert!(snake.occupancy().is_occupied((2, 1).into()));
+
+        let cherry = Pickup::new(PickupKind::Cherry, (2, 2));
+        assert!(snake.advance(&[cherry]).is_ok());
+        assert_eq!(snake.occupancy().free_count(), 12);
+        for (point, _, _) in snake.parts() {
+            assert!(snake.occupancy().is_occupied(*point));
+        }
+    }
+
+    #[test]
+    fn test_overlapping_parts() {
+        // the snake grew into its tail, the head went on
+        let parts = vec![
+            ((1, 0).into(), BodyPartKind::Head, Direction::Up),
+            ((1, 1).into(), BodyPartKind::Middle, Direction::Up),
+            ((1, 2).into(), BodyPartKind::Middle, Direction::Left),
+            ((2, 2).into(), BodyPartKind::Middle, Direction::Down),
+            ((2, 1).into(), BodyPartKind::Middle, Direction::Right),
+            ((1, 1).into(), BodyPartKind::Tail, Direction::Right),
+        ];
+        let mut snake = Snake::from_parts(parts, Direction::Up, Direction::Left, (5, 5))
+            .expect("Overlapping parts make a snake");
+        assert_eq!(snake.occupancy().free_count(), 20);
+
+        // the cell stays taken while one of its two parts is left
+        assert!(snake.advance(&[far_pickup()]).is_ok());
+        assert!(snake.occupancy().is_occupied((1, 1).into()));
+        assert_eq!(snake.occupancy().free_count(), 19);
+    }
+
+    #[test]
+    fn test_ghost_goes_through_the_body() {
+        let mut snake = Snake::new((4, 1), 5, (8, 8));
+        snake.set_ghost(true);
+
+        for direction in &[
+            Direction::Down,
+            Direction::Left,
+            Direction::Up,
+            Direction::Up,
+        ] {
+            snake.set_next_direction(*direction);
+            assert!(snake.advance(&[far_pickup()]).is_ok());
+        }
+        assert_eq!(snake.head(), (3, 0).into());
+
+        // the crossing stays taken while one of its two parts is left
+        snake.set_ghost(false);
+        snake.set_next_direction(Direction::Right);
+        assert!(snake.advance(&[far_pickup()]).is_ok());
+        assert!(snake.occupancy().is_occupied((3, 1).into()));
+        assert_eq!(snake.occupancy().free_count(), 59);
+
+        snake.set_next_direction(Direction::Down);
+        assert!(snake.advance(&[far_pickup()]).is_ok());
+        snake.set_next_direction(Direction::Left);
+        assert_eq!(
+            snake.advance(&[far_pickup()]).err(),
+            Some(Collision::Body((3, 1).into()))
+        );
+    }
+
+    #[test]
+    fn test_portals() {
+        let mut snake = Snake::new((4, 1), 3, (8, 8));
+        assert!(snake.set_portals(vec![PortalPair::new((5, 1), (2, 5))]));
+        assert_eq!(snake.occupancy().free_count(), 59);
+
+        // the head comes out past the other end, the body follows it through
+        let expected = [
+            vec![
+                ((3, 5).into(), BodyPartKind::Head, Direction::Right),
+                ((4, 1).into(), BodyPartKind::Middle, Direction::Right),
+                ((3, 1).into(), BodyPartKind::Tail, Direction::Right),
+            ],
+            vec![
+                ((4, 5).into(), BodyPartKind::Head, Direction::Right),
+                ((3, 5).into(), BodyPartKind::Middle, Direction::Right),
+                ((4, 1).into(), BodyPartKind::Tail, Direction::Right),
+            ],
+            vec![
+                ((5, 5).into(), BodyPartKind::Head, Direction::Right),
+                ((4, 5).into(), BodyPartKind::Middle, Direction::Right),
+                ((3, 5).into(), BodyPartKind::Tail, Direction::Right),
+            ],
+        ];
+        for parts in &expected {
+            assert!(snake.advance(&[far_pickup()]).is_ok());
+            assert_eq!(&snake.parts().cloned().collect::<Vec<_>>(), parts);
+        }
+        assert_eq!(snake.occupancy().free_count(), 59);
+        assert!(snake.occupancy().is_occupied((5, 1).into()));
+        assert!(!snake.occupancy().is_occupied((4, 1).into()));
+    }
+
+    #[test]
+    fn test_portal_collisions() {
+        // coming out at the border
+        let mut snake = Snake::new((4, 1), 5, (8, 8));
+        assert!(snake.set_portals(vec![PortalPair::new((5, 1), (7, 6))]));
+        assert_eq!(
+            snake.advance(&[far_pickup()]).err(),
+            Some(Collision::Border((8, 6).into()))
+        );
+
+        // and into the body
+        let mut snake = Snake::new((4, 1), 5, (8, 8));
+        assert!(snake.set_portals(vec![PortalPair::new((4, 2), (2, 0))]));
+        snake.set_next_direction(Direction::Down);
+        assert_eq!(
+            snake.advance(&[far_pickup()]).err(),
+            Some(Collision::Body((2, 1).into()))
+        );
+
+        // portals on the body or next to another end are refused, the old ones are kept
+        assert!(!snake.set_portals(vec![PortalPair::new((3, 1), (6, 6))]));
+        assert!(!snake.set_portals(vec![PortalPair::new((6, 6), (6, 5))]));
+        assert!(!snake.set_portals(vec![
+            PortalPair::new((6, 6), (0, 5)),
+            PortalPair::new((6, 3), (1, 5)),
+        ]));
+        assert_eq!(snake.portals(), &[PortalPair::new((4, 2), (2, 0))]);
+        assert_eq!(snake.occupancy().free_count(), 57);
+
+        assert!(snake.set_portals(Vec::new()));
+        assert_eq!(snake.occupancy().free_count(), 59);
+    }
+
+    #[test]
+    fn test_grow() {
+        let mut snake = Snake::new((4, 1), 5, (8, 8));
+        snake.grow();
+        snake.grow();
+
+        for length in &[6, 7, 7] {
+            assert!(snake.advance(&[far_pickup()]).is_ok());
+            assert_eq!(snake.length(), *length);
+        }
+        assert_eq!(
+            snake.parts().last().map(|(point, _, _)| *point),
+            Some((1, 1).into())
+        );
+    }
+
+    #[test]
+    fn test_shrink() {
+        let mut snake = Snake::new((4, 1), 5, (8, 8));
+        snake.set_next_direction(Direction::Down);
+        assert!(snake.advance(&[far_pickup()]).is_ok());
+
+        snake.shrink(2);
+        assert_eq!(snake.length(), 3);
+        assert_eq!(snake.occupancy().free_count(), 61);
+        assert!(!snake.occupancy().is_occupied((1, 1).into()));
+        assert_eq!(
+            snake.parts().last(),
+            Some(&((3, 1).into(), BodyPartKind::Tail, Direction::Right))
+        );
+
+        snake.shrink(10);
+        assert_eq!(snake.length(), 2);
+        assert_eq!(snake.occupancy().free_count(), 62);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_tick_by_length() {
+        let ticks = 10_000;
+
+        for &length in &[10_u32, 1_000, 100_000] {
+            // a single row, long enough for the snake to go straight for all the ticks
+            let mut snake = Snake::new((length as i32 - 1, 0), length, (length + ticks, 1));
+            let start = Instant::now();
+
+            for _ in 0..ticks {
+                assert!(snake.advance(&[far_pickup()]).is_ok());
+            }
+
+            println!("length {}: {:?} per tick", length, start.elapsed() / ticks);
+        }
+    }
+}
@@ -1,74 +1,85 @@
-use crate::game::font::get_font;
-use crate::game::font::Font;
-use image::imageops::FilterType;
-use image::{DynamicImage, GenericImageView};
-use opengl_graphics::{Texture, TextureSettings};
+use crate::assets::{AssetError, AssetSource, FONT_LAYOUT, FONT_SHEET, SNAKE_SHEET};
+use crate::game::font::{Font, FontLayout};
+use crate::sprite_batch::Sprite;
+use image::{imageops, GenericImageView, Rgba, RgbaImage};
+use opengl_graphics::{Filter, Texture, TextureSettings};
 
+/// Side of a sprite in the sheets, in pixels.
+pub const SPRITE_SIZE: u32 = 8;
+
+/// All sprites, packed into a single atlas texture.
+///
+/// The snake sheet is placed at the top left corner of the atlas, the font sheet under it
+/// and a solid white square for tinted shapes under the font.
 pub struct SpriteData {
-    pub brick: Texture,
-    pub snake_head: [Texture; 4],
-    pub snake_body: [Texture; 4],
-    pub snake_tail: [Texture; 4],
-    pub cherry: Texture,
-    pub apple: Texture,
+    pub atlas: Texture,
+    pub solid: Sprite,
+    pub brick: Sprite,
+    pub snake_head: Sprite,
+    pub snake_body: Sprite,
+    pub snake_tail: Sprite,
+    pub cherry: Sprite,
+    pub apple: Sprite,
     pub font: Font,
 }
 
-fn get_texture(image: &DynamicImage, x: u32, y: u32, size: u32) -> Texture {
-    Texture::from_image(
-        &image.view(x * size, y * size, size, size).to_image(),
-        &TextureSettings::new(),
+fn get_sprite(x: u32, y: u32, atlas_size: [u32; 2]) -> Sprite {
+    Sprite::new(
+        [x * SPRITE_SIZE, y * SPRITE_SIZE, SPRITE_SIZE, SPRITE_SIZE],
+        atlas_size,
     )
 }
 
-fn get_textures(image: &DynamicImage, x: u32, y: u32, size: u32) -> [Texture; 4] {
-    let cropped_image_buffer = image.view(x * size, y * size, size, size).to_image();
-    let cropped_image = DynamicImage::ImageRgba8(cropped_image_buffer);
-
-    let texture_settings = &TextureSettings::new();
-    [
-        Texture::from_image(&cropped_image.to_rgba(), texture_settings),
-        Texture::from_image(&cropped_image.rotate90().to_rgba(), texture_settings),
-        Texture::from_image(&cropped_image.rotate180().to_rgba(), texture_settings),
-        Texture::from_image(&cropped_image.rotate270().to_rgba(), texture_settings),
-    ]
-}
-
 impl SpriteData {
-    pub fn new(scale: u32) -> Self {
-        let snake_data = include_bytes!("../resources/snake.png");
-        let font_data = include_bytes!("../resources/font.png");
-
-        let mut snake_image =
-            image::load_from_memory_with_format(snake_data, image::ImageFormat::PNG)
-                .expect("Failed to load the texture.");
+    pub fn load(assets: &AssetSource) -> Result<Self, AssetError> {
+        let snake_image = assets.load_image(SNAKE_SHEET)?;
+        let font_image = assets.load_image(FONT_SHEET)?;
+        let font_layout = FontLayout::from_config(&assets.load_config(FONT_LAYOUT)?)
+            .map_err(|error| AssetError::Config(FONT_LAYOUT.to_string(), error))?;
 
-        let mut font_image =
-            image::load_from_memory_with_format(font_data, image::ImageFormat::PNG)
-                .expect("Failed to load the font.");
+        let font_origin = [0, snake_image.height()];
+        let solid_origin = [0, font_origin[1] + font_image.height()];
+        let atlas_size = [
+            snake_image.width().max(font_image.width()),
+            solid_origin[1] + SPRITE_SIZE,
+        ];
 
-        snake_image = snake_image.resize(
-            snake_image.width() * scale,
-            snake_image.height() * scale,
-            FilterType::Nearest,
-        );
-
-        font_image = font_image.resize(
-            font_image.width() * scale,
-            font_image.height() * scale,
-            FilterType::Nearest,
+        let mut atlas_image = RgbaImage::new(atlas_size[0], atlas_size[1]);
+        imageops::replace(&mut atlas_image, &snake_image.to_rgba(), 0, 0);
+        imageops::replace(
+            &mut atlas_image,
+            &font_image.to_rgba(),
+            font_origin[0],
+            font_origin[1],
         );
+        for y in solid_origin[1]..solid_origin[1] + SPRITE_SIZE {
+            for x in solid_origin[0]..solid_origin[0] + SPRITE_SIZE {
+                atlas_image.put_pixel(x, y, Rgba([255; 4]));
+            }
+        }
 
-        let texture_size = 8 * scale;
+        // sprites are scaled up when drawn, nearest filtering keeps the pixels sharp
+        let texture_settings = TextureSettings::new().filter(Filter::Nearest);
 
-        SpriteData {
-            brick: get_texture(&snake_image, 0, 0, texture_size),
-            snake_head: get_textures(&snake_image, 1, 0, texture_size),
-            snake_body: get_textures(&snake_image, 2, 0, texture_size),
-            snake_tail: get_textures(&snake_image, 2, 1, texture_size),
-            cherry: get_texture(&snake_image, 0, 1, texture_size),
-            apple: get_texture(&snake_image, 1, 1, texture_size),
-            font: get_font(&font_image, 16, texture_size),
-        }
+        Ok(SpriteData {
+            atlas: Texture::from_image(&atlas_image, &texture_settings),
+            solid: Sprite::new(
+                [solid_origin[0], solid_origin[1], SPRITE_SIZE, SPRITE_SIZE],
+                atlas_size,
+            ),
+            brick: get_sprite(0, 0, atlas_size),
+            snake_head: get_sprite(1, 0, atlas_size),
+            snake_body: get_sprite(2, 0, atlas_size),
+            snake_tail: get_sprite(2, 1, atlas_size),
+            cherry: get_sprite(0, 1, atlas_size),
+            apple: get_sprite(1, 1, atlas_size),
+            font: Font::new(
+                &font_layout,
+                font_origin,
+                [font_image.width(), font_image.height()],
+                SPRITE_SIZE,
+                atlas_size,
+            ),
+        })
     }
 }
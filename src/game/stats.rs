@@ -0,0 +1,264 @@
+use crate::config::Config;
+use crate::game::mode::GameMode;
+use crate::game::save::{parse_list, SaveError};
+use crate::game::snake::Collision;
+use crate::size::Size;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub const STATS_FILE: &str = "stats.cfg";
+
+const STATS_VERSION: u32 = 1;
+
+/// How a game ended.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameEnd {
+    Crashed(Collision),
+    /// The snake filled the whole field.
+    BoardCleared,
+    /// The time of the game ran out.
+    TimeUp,
+}
+
+/// How a finished game went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSummary {
+    pub mode: GameMode,
+    /// Code of the seed, when the game was seeded.
+    pub seed: Option<String>,
+    pub game_size: Size,
+    pub score: u32,
+    pub cherries_eaten: u32,
+    pub snake_length: usize,
+    pub play_time: Duration,
+    pub end: GameEnd,
+}
+
+/// Totals over all the games of a profile.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stats {
+    pub games_played: u32,
+    pub cherries_eaten: u32,
+    pub longest_snake: usize,
+    pub play_time: Duration,
+    pub boards_cleared: u32,
+    /// Number of deaths by the key of the cause.
+    pub deaths: BTreeMap<String, u32>,
+    /// Best score by the mode and the board width and height.
+    pub best_scores: BTreeMap<(GameMode, u32, u32), u32>,
+}
+
+/// Name of the cause in the files, which doesn't change with the wording on the screen.
+pub fn death_cause_key(cause: &Collision) -> &'static str {
+    match cause {
+        Collision::Border(_) => "wall",
+        Collision::Body(_) => "body",
+        Collision::Hazard(_) => "hazard",
+    }
+}
+
+pub fn death_cause_title(key: &str) -> &str {
+    match key {
+        "wall" => "Hit the wall",
+        "body" => "Bit itself",
+        "hazard" => "Hit a hazard",
+        _ => key,
+    }
+}
+
+/// Formats the time as `h:mm:ss`.
+pub fn format_play_time(play_time: Duration) -> String {
+    let seconds = play_time.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+impl Stats {
+    /// Adds the game to the totals, returns whether it beat the best score of its board and mode.
+    pub fn record(&mut self, summary: &GameSummary) -> bool {
+        self.games_played += 1;
+        self.cherries_eaten += summary.cherries_eaten;
+        self.longest_snake = self.longest_snake.max(summary.snake_length);
+        self.play_time += summary.play_time;
+        match &summary.end {
+            GameEnd::Crashed(cause) => {
+                *self
+                    .deaths
+                    .entry(death_cause_key(cause).to_string())
+                    .or_insert(0) += 1
+            }
+            GameEnd::BoardCleared => self.boards_cleared += 1,
+            GameEnd::TimeUp => (),
+        }
+
+        let board = (
+            summary.mode,
+            summary.game_size.width,
+            summary.game_size.height,
+        );
+        match self.best_scores.get(&board) {
+            Some(best) if *best >= summary.score => false,
+            _ => {
+                self.best_scores.insert(board, summary.score);
+                summary.score > 0
+            }
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let deaths: Vec<String> = self
+            .deaths
+            .iter()
+            .map(|(cause, count)| format!("{} {}", cause, count))
+            .collect();
+        let best_scores: Vec<String> = self
+            .best_scores
+            .iter()
+            .map(|((mode, width, height), score)| {
+                format!("{} {}x{} {}", mode.key(), width, height, score)
+            })
+            .collect();
+
+        let lines = [
+            "# Lifetime statistics of the profile".to_string(),
+            format!("version = {}", STATS_VERSION),
+            format!("games_played = {}", self.games_played),
+            format!("cherries_eaten = {}", self.cherries_eaten),
+            format!("longest_snake = {}", self.longest_snake),
+            format!("play_time_s = {}", self.play_time.as_secs()),
+            format!("boards_cleared = {}", self.boards_cleared),
+            format!("deaths = {}", deaths.join(", ")),
+            format!("best_scores = {}", best_scores.join(", ")),
+        ];
+
+        lines.join("\n") + "\n"
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        let config = Config::parse(text)?;
+
+        let version: u32 = config.get("version")?.unwrap_or(STATS_VERSION);
+        if version != STATS_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+
+        let deaths = parse_list(&config, "deaths", |words| match words {
+            [cause, count] => Some((cause.to_string(), count.parse().ok()?)),
+            _ => None,
+        })?;
+        let best_scores = parse_list(&config, "best_scores", |words| match words {
+            [mode, board, score] => {
+                let mut board = board.splitn(2, 'x');
+                let width = board.next()?.parse().ok()?;
+                let height = board.next()?.parse().ok()?;
+                Some((
+                    (GameMode::from_key(mode)?, width, height),
+                    score.parse().ok()?,
+                ))
+            }
+            _ => None,
+        })?;
+
+        Ok(Stats {
+            games_played: config.get("games_played")?.unwrap_or(0),
+            cherries_eaten: config.get("cherries_eaten")?.unwrap_or(0),
+            longest_snake: config.get("longest_snake")?.unwrap_or(0),
+            play_time: Duration::from_secs(config.get("play_time_s")?.unwrap_or(0)),
+            boards_cleared: config.get("boards_cleared")?.unwrap_or(0),
+            deaths: deaths.into_iter().collect(),
+            best_scores: best_scores.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn summary(score: u32, cause: Collision) -> GameSummary {
+        GameSummary {
+            mode: GameMode::Classic,
+            seed: None,
+            game_size: (24, 16).into(),
+            score,
+            cherries_eaten: score,
+            snake_length: 5 + score as usize,
+            play_time: Duration::from_secs(90),
+            end: GameEnd::Crashed(cause),
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let mut stats = Stats::default();
+
+        assert!(stats.record(&summary(3, Collision::Border((-1, 0).into()))));
+        assert!(!stats.record(&summary(2, Collision::Body((1, 1).into()))));
+        assert!(stats.record(&summary(4, Collision::Border((0, -1).into()))));
+        // a different board has its own best score
+        let mut small_board = summary(1, Collision::Body((0, 0).into()));
+        small_board.game_size = (12, 8).into();
+        assert!(stats.record(&small_board));
+
+        assert_eq!(stats.games_played, 4);
+        assert_eq!(stats.cherries_eaten, 10);
+        assert_eq!(stats.longest_snake, 9);
+        assert_eq!(stats.play_time, Duration::from_secs(360));
+        assert_eq!(stats.deaths["wall"], 2);
+        assert_eq!(stats.deaths["body"], 2);
+        assert_eq!(stats.best_scores[&(GameMode::Classic, 24, 16)], 4);
+        assert_eq!(stats.best_scores[&(GameMode::Classic, 12, 8)], 1);
+    }
+
+    #[test]
+    fn test_record_cleared_board() {
+        let mut stats = Stats::default();
+        let cleared = GameSummary {
+            end: GameEnd::BoardCleared,
+            ..summary(100, Collision::Body((0, 0).into()))
+        };
+
+        assert!(stats.record(&cleared));
+        assert_eq!(stats.boards_cleared, 1);
+        assert!(stats.deaths.is_empty());
+        assert_eq!(Stats::parse(&stats.to_text()), Ok(stats));
+    }
+
+    #[test]
+    fn test_zero_score_is_not_a_record() {
+        let mut stats = Stats::default();
+        assert!(!stats.record(&summary(0, Collision::Body((0, 0).into()))));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut stats = Stats::default();
+        assert_eq!(Stats::parse(&stats.to_text()), Ok(stats.clone()));
+
+        stats.record(&summary(3, Collision::Border((-1, 0).into())));
+        stats.record(&summary(1, Collision::Body((1, 1).into())));
+        assert_eq!(Stats::parse(&stats.to_text()), Ok(stats.clone()));
+
+        assert!(stats.to_text().contains("best_scores = classic 24x16 3\n"));
+    }
+
+    #[test]
+    fn test_invalid_stats() {
+        assert!(Stats::parse("best_scores = classic 24x 3").is_err());
+        assert!(Stats::parse("deaths = wall many").is_err());
+        assert_eq!(
+            Stats::parse("version = 9"),
+            Err(SaveError::UnsupportedVersion(9))
+        );
+    }
+
+    #[test]
+    fn test_format_play_time() {
+        assert_eq!(format_play_time(Duration::from_secs(59)), "0:00:59");
+        assert_eq!(format_play_time(Duration::from_secs(3723)), "1:02:03");
+    }
+}
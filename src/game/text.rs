@@ -0,0 +1,188 @@
+use crate::game::font::{draw_string, Font};
+use crate::point::Point;
+use crate::size::Size;
+use crate::sprite_renderer::GenericContext;
+use graphics::types::Color;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// Width of the text in cells, as drawn by `draw_string`.
+pub fn text_width<S: AsRef<str>>(text: S) -> u32 {
+    text.as_ref().to_uppercase().chars().count() as u32
+}
+
+/// Column where a line of `line_width` cells starts inside of `area_width` cells.
+pub fn aligned_column(line_width: u32, area_width: u32, alignment: Alignment) -> i32 {
+    let free_space = area_width.saturating_sub(line_width) as i32;
+
+    match alignment {
+        Alignment::Left => 0,
+        Alignment::Center => free_space / 2,
+        Alignment::Right => free_space,
+    }
+}
+
+/// Splits the text into lines no wider than `width`.
+///
+/// Lines are broken between words where possible, words longer than a line are split.
+/// Explicit line breaks in the text are kept.
+pub fn wrap<S: AsRef<str>>(text: S, width: u32) -> Vec<String> {
+    let width = width.max(1) as usize;
+    let mut lines = Vec::new();
+
+    for paragraph in text.as_ref().to_uppercase().split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+
+            if line_width > 0 && line_width + 1 + word.len() <= width {
+                line.push(' ');
+                line_width += 1;
+            } else if line_width > 0 {
+                lines.push(line);
+                line = String::new();
+                line_width = 0;
+            }
+
+            while word.len() > width {
+                let rest = word.split_off(width);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+
+            line_width += word.len();
+            line.extend(word);
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Draws a single line on the given row, aligned within the width of the context.
+pub fn draw_string_aligned<C, S>(
+    context: &mut C,
+    string: S,
+    row: i32,
+    alignment: Alignment,
+    color: Color,
+    font: &Font,
+) where
+    C: GenericContext,
+    S: AsRef<str>,
+{
+    let column = aligned_column(text_width(&string), context.width(), alignment);
+    draw_string(context, string, (column, row), color, font);
+}
+
+/// Draws wrapped text into the bounding box, lines which don't fit are dropped.
+///
+/// Returns the number of lines drawn.
+pub fn draw_paragraph<C, S, P, Z>(
+    context: &mut C,
+    text: S,
+    position: P,
+    size: Z,
+    alignment: Alignment,
+    color: Color,
+    font: &Font,
+) -> u32
+where
+    C: GenericContext,
+    S: AsRef<str>,
+    P: Into<Point>,
+    Z: Into<Size>,
+{
+    let position: Point = position.into();
+    let size: Size = size.into();
+
+    let lines = wrap(text, size.width);
+    let visible_lines = lines.len().min(size.height as usize);
+
+    for (index, line) in lines.iter().take(visible_lines).enumerate() {
+        let column = aligned_column(text_width(line), size.width, alignment);
+        draw_string(
+            context,
+            line,
+            position.offset((column, index as i32)),
+            color,
+            font,
+        );
+    }
+
+    visible_lines as u32
+}
+
+/// Draws the paragraphs one under another with an empty line between them,
+/// centered both horizontally and vertically.
+pub fn draw_centered_paragraphs<C, S>(context: &mut C, paragraphs: &[(S, Color)], font: &Font)
+where
+    C: GenericContext,
+    S: AsRef<str>,
+{
+    let size = context.size();
+
+    let lines: u32 = paragraphs
+        .iter()
+        .map(|(text, _)| wrap(text, size.width).len() as u32)
+        .sum();
+    let height = lines + paragraphs.len().saturating_sub(1) as u32;
+    let mut top = size.height.saturating_sub(height) / 2;
+
+    for (text, color) in paragraphs {
+        let drawn = draw_paragraph(
+            context,
+            text,
+            (0, top),
+            (size.width, size.height.saturating_sub(top)),
+            Alignment::Center,
+            *color,
+            font,
+        );
+        top += drawn + 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_text_width() {
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("score: 10"), 9);
+        // uppercase of 'ß' takes two cells
+        assert_eq!(text_width("straße"), 7);
+    }
+
+    #[test]
+    fn test_aligned_column() {
+        assert_eq!(aligned_column(9, 24, Alignment::Left), 0);
+        assert_eq!(aligned_column(9, 24, Alignment::Center), 7);
+        assert_eq!(aligned_column(9, 24, Alignment::Right), 15);
+        assert_eq!(aligned_column(30, 24, Alignment::Center), 0);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("Press SPACE to restart", 24),
+            vec!["PRESS SPACE TO RESTART"]
+        );
+        assert_eq!(
+            wrap("Press SPACE to restart", 12),
+            vec!["PRESS SPACE", "TO RESTART"]
+        );
+        assert_eq!(wrap("a  b\n\nc", 10), vec!["A B", "", "C"]);
+        assert_eq!(wrap("abcdefgh ij", 3), vec!["ABC", "DEF", "GH", "IJ"]);
+        assert_eq!(wrap("", 5), vec![""]);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::game::font::Font;
+use crate::game::palette;
+use crate::game::text::{draw_string_aligned, Alignment};
+use crate::sprite_batch::Sprite;
+use crate::sprite_renderer::GenericContext;
+use graphics::types::Color;
+use std::collections::VecDeque;
+
+const TOAST_DURATION: f64 = 3.0;
+const TOAST_FADE: f64 = 0.3;
+
+struct Toast {
+    text: String,
+    color: Color,
+}
+
+/// Announcements shown one after another over the bottom row of the screen.
+///
+/// Times are measured in seconds.
+#[derive(Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+    // time the first toast of the queue has been shown for
+    age: f64,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Toasts::default()
+    }
+
+    pub fn show(&mut self, text: String, color: Color) {
+        self.queue.push_back(Toast { text, color });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        if self.queue.is_empty() {
+            return;
+        }
+
+        self.age += dt;
+        if self.age >= TOAST_DURATION {
+            self.queue.pop_front();
+            self.age = 0.0;
+        }
+    }
+
+    pub fn render<C>(&self, context: &mut C, solid: &Sprite, font: &Font)
+    where
+        C: GenericContext,
+    {
+        let toast = match self.queue.front() {
+            Some(toast) => toast,
+            None => return,
+        };
+
+        let alpha = (self.age.min(TOAST_DURATION - self.age) / TOAST_FADE).clamp(0.0, 1.0) as f32;
+        let size = context.size();
+        let row = size.height as i32 - 1;
+        context.draw_sprite_at(
+            [0.0, row as f64],
+            [size.width as f64, 1.0],
+            solid,
+            palette::with_alpha(palette::BLACK, alpha),
+        );
+        draw_string_aligned(
+            context,
+            &toast.text,
+            row,
+            Alignment::Center,
+            palette::with_alpha(toast.color, alpha),
+            font,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_one_after_another() {
+        let mut toasts = Toasts::new();
+        toasts.show("first".to_string(), palette::WHITE);
+        toasts.show("second".to_string(), palette::WHITE);
+
+        toasts.update(TOAST_DURATION - 0.1);
+        assert_eq!(toasts.queue.front().unwrap().text, "first");
+        toasts.update(0.1);
+        assert_eq!(toasts.queue.front().unwrap().text, "second");
+        toasts.update(TOAST_DURATION);
+        assert!(toasts.is_empty());
+    }
+}
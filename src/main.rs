@@ -6,47 +6,64 @@ extern crate piston;
 
 #[macro_use]
 mod conv_macros;
+mod assets;
+mod audio;
+mod clock;
+mod config;
 mod game;
 mod point;
 mod size;
+mod sprite_batch;
 mod sprite_renderer;
+mod storage;
 
 use glutin_window::GlutinWindow as Window;
 use opengl_graphics::OpenGL;
 use piston::event_loop::*;
+use piston::input::{Button, Key, PressEvent};
 use piston::window::WindowSettings;
 
-use game::{Game, GameSettings};
-
-const GAME_WIDTH: u32 = 24;
-const GAME_HEIGHT: u32 = 16;
-const SPRITE_SIZE: u32 = 8;
-const SPRITE_SCALE: u32 = 3;
+use assets::AssetSource;
+use game::{Game, GameSettings, SPRITE_SIZE};
 
 fn main() {
+    let assets = AssetSource::from_args(std::env::args().skip(1));
+
+    let settings = GameSettings::load(OpenGL::V2_1, SPRITE_SIZE, &assets)
+        .unwrap_or_else(|error| panic!("Failed to load the settings: {}", error));
+
     let opengl = OpenGL::V2_0;
 
-    let mut window: Window = WindowSettings::new(
-        "Snake Game",
-        [
-            GAME_WIDTH * SPRITE_SIZE * SPRITE_SCALE,
-            GAME_HEIGHT * SPRITE_SIZE * SPRITE_SCALE,
-        ],
-    )
-    .opengl(opengl)
-    .exit_on_esc(false)
-    .resizable(false)
-    .vsync(true)
-    .build()
-    .unwrap();
-
-    let opengl = OpenGL::V2_1;
-
-    let settings = GameSettings::new(opengl, SPRITE_SIZE, SPRITE_SCALE, (GAME_WIDTH, GAME_HEIGHT));
-    let mut game = Game::new(settings);
+    let mut window: Window = WindowSettings::new("Snake Game", settings.window_size())
+        .opengl(opengl)
+        .exit_on_esc(false)
+        .resizable(true)
+        .fullscreen(settings.fullscreen())
+        .vsync(true)
+        .build()
+        .unwrap();
+
+    let mut fullscreen = settings.fullscreen();
+    let mut game = Game::new(settings, assets);
 
     let mut events = Events::new(EventSettings::new());
     while let Some(event) = events.next(&mut window) {
+        if let Some(Button::Keyboard(Key::F11)) = event.press_args() {
+            fullscreen = !fullscreen;
+            set_fullscreen(&window, fullscreen);
+        }
+
         game.handle_event(&event);
     }
+
+    game.quit();
+}
+
+fn set_fullscreen(window: &Window, fullscreen: bool) {
+    let monitor = match fullscreen {
+        true => Some(window.window.get_current_monitor()),
+        false => None,
+    };
+
+    window.window.set_fullscreen(monitor);
 }
@@ -1,4 +1,4 @@
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -23,7 +23,7 @@ impl Point {
 
 impl From<&Point> for Point {
     fn from(point: &Point) -> Point {
-        (*point).clone()
+        *point
     }
 }
 
@@ -31,7 +31,7 @@ impl From<Point> for Offset {
     fn from(point: Point) -> Offset {
         Offset {
             delta_x: point.x,
-            delta_y: point.y
+            delta_y: point.y,
         }
     }
 }
@@ -41,4 +41,4 @@ define_conversions! {
     Point, u32, i32, [x, y];
     Offset, i32, i32, [delta_x, delta_y];
     Offset, u32, i32, [delta_x, delta_y];
-}
\ No newline at end of file
+}
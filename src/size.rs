@@ -6,4 +6,4 @@ pub struct Size {
 
 define_conversions! {
     Size, u32, u32, [width, height];
-}
\ No newline at end of file
+}
@@ -0,0 +1,278 @@
+use graphics::math::{transform_pos, Matrix2d};
+use graphics::types::Color;
+use graphics::{DrawState, Graphics, BACK_END_MAX_VERTEX_COUNT};
+use opengl_graphics::{GlGraphics, Texture};
+
+// whole quads only, so that a chunk never splits a triangle
+const CHUNK_VERTEX_COUNT: usize = BACK_END_MAX_VERTEX_COUNT / 6 * 6;
+
+/// Region of the atlas texture, in texture coordinates.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Sprite {
+    uv: [f32; 4],
+}
+
+/// Number of clockwise quarter turns applied to a sprite when drawn.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Rotation {
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+/// Collects the sprites of a frame and submits them in as few draw calls as possible.
+///
+/// All sprites come from the same atlas texture, so consecutive sprites only need a new
+/// draw call when their tint changes.
+pub struct SpriteBatch {
+    vertices: Vec<[f32; 2]>,
+    texture_coords: Vec<[f32; 2]>,
+    // tint and the number of vertices drawn with it
+    runs: Vec<(Color, usize)>,
+}
+
+impl Sprite {
+    /// Creates the sprite from a pixel rectangle `[x, y, width, height]` of the atlas.
+    pub fn new(rect: [u32; 4], atlas_size: [u32; 2]) -> Self {
+        let [x, y, width, height] = rect;
+        let [atlas_width, atlas_height] = atlas_size;
+        let atlas_width = atlas_width as f32;
+        let atlas_height = atlas_height as f32;
+
+        Sprite {
+            uv: [
+                x as f32 / atlas_width,
+                y as f32 / atlas_height,
+                (x + width) as f32 / atlas_width,
+                (y + height) as f32 / atlas_height,
+            ],
+        }
+    }
+
+    /// Texture coordinates of the corners, clockwise from the top left one.
+    fn corners(&self, rotation: Rotation) -> [[f32; 2]; 4] {
+        let [left, top, right, bottom] = self.uv;
+        let corners = [[left, top], [right, top], [right, bottom], [left, bottom]];
+
+        // turning the sprite clockwise brings the corner on the left of each one into its place
+        let turns = rotation.quarter_turns();
+        [
+            corners[(4 - turns) % 4],
+            corners[(5 - turns) % 4],
+            corners[(6 - turns) % 4],
+            corners[(7 - turns) % 4],
+        ]
+    }
+}
+
+impl Rotation {
+    fn quarter_turns(self) -> usize {
+        match self {
+            Rotation::None => 0,
+            Rotation::Quarter => 1,
+            Rotation::Half => 2,
+            Rotation::ThreeQuarters => 3,
+        }
+    }
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        SpriteBatch::new()
+    }
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        SpriteBatch {
+            vertices: Vec::new(),
+            texture_coords: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Adds a sprite stretched to `size` at `position`, both in the space of the transform.
+    pub fn push(
+        &mut self,
+        transform: Matrix2d,
+        position: [f64; 2],
+        size: [f64; 2],
+        sprite: &Sprite,
+        rotation: Rotation,
+        color: Color,
+    ) {
+        let [x, y] = position;
+        let [width, height] = size;
+        let corners = [
+            transform_pos(transform, [x, y]),
+            transform_pos(transform, [x + width, y]),
+            transform_pos(transform, [x + width, y + height]),
+            transform_pos(transform, [x, y + height]),
+        ];
+        let texture_coords = sprite.corners(rotation);
+
+        for &index in &[0, 1, 2, 0, 2, 3] {
+            let [x, y] = corners[index];
+            self.vertices.push([x as f32, y as f32]);
+            self.texture_coords.push(texture_coords[index]);
+        }
+
+        match self.runs.last_mut() {
+            Some((run_color, count)) if *run_color == color => *count += 6,
+            _ => self.runs.push((color, 6)),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.texture_coords.clear();
+        self.runs.clear();
+    }
+
+    /// Draws everything collected so far and empties the batch.
+    pub fn submit(&mut self, gl: &mut GlGraphics, draw_state: &DrawState, atlas: &Texture) {
+        let mut start = 0;
+
+        for (color, count) in &self.runs {
+            let vertices = &self.vertices[start..start + count];
+            let texture_coords = &self.texture_coords[start..start + count];
+
+            gl.tri_list_uv(draw_state, color, atlas, |f| {
+                for (vertices, texture_coords) in vertices
+                    .chunks(CHUNK_VERTEX_COUNT)
+                    .zip(texture_coords.chunks(CHUNK_VERTEX_COUNT))
+                {
+                    f(vertices, texture_coords);
+                }
+            });
+
+            start += count;
+        }
+
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphics::math::identity;
+    use std::time::Instant;
+
+    const WHITE: Color = [1.0; 4];
+    const RED: Color = [1.0, 0.0, 0.0, 1.0];
+
+    fn sprite() -> Sprite {
+        Sprite::new([8, 0, 8, 8], [32, 16])
+    }
+
+    /// Number of sprites and draw calls `submit` would make.
+    fn stats(batch: &SpriteBatch) -> (usize, usize) {
+        (batch.vertices.len() / 6, batch.runs.len())
+    }
+
+    #[test]
+    fn test_sprite_uv() {
+        assert_eq!(sprite().uv, [0.25, 0.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let [top_left, top_right, bottom_right, bottom_left] = sprite().corners(Rotation::None);
+
+        assert_eq!(
+            sprite().corners(Rotation::Quarter),
+            [bottom_left, top_left, top_right, bottom_right]
+        );
+        assert_eq!(
+            sprite().corners(Rotation::Half),
+            [bottom_right, bottom_left, top_left, top_right]
+        );
+        assert_eq!(
+            sprite().corners(Rotation::ThreeQuarters),
+            [top_right, bottom_right, bottom_left, top_left]
+        );
+    }
+
+    #[test]
+    fn test_push_transforms_corners() {
+        let mut batch = SpriteBatch::new();
+        batch.push(
+            identity(),
+            [2.0, 3.0],
+            [8.0, 4.0],
+            &sprite(),
+            Rotation::None,
+            WHITE,
+        );
+
+        assert_eq!(
+            batch.vertices,
+            vec![
+                [2.0, 3.0],
+                [10.0, 3.0],
+                [10.0, 7.0],
+                [2.0, 3.0],
+                [10.0, 7.0],
+                [2.0, 7.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_runs_split_by_color() {
+        let mut batch = SpriteBatch::new();
+        for color in &[WHITE, WHITE, RED, RED, WHITE] {
+            batch.push(
+                identity(),
+                [0.0, 0.0],
+                [8.0, 8.0],
+                &sprite(),
+                Rotation::None,
+                *color,
+            );
+        }
+
+        assert_eq!(stats(&batch), (5, 3));
+
+        batch.clear();
+        assert_eq!(stats(&batch), (0, 0));
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_large_board() {
+        for &side in &[24_u32, 100, 500] {
+            let mut batch = SpriteBatch::new();
+            let start = Instant::now();
+
+            for y in 0..side {
+                for x in 0..side {
+                    // a tinted snake running through an untinted board
+                    let color = if y == side / 2 { RED } else { WHITE };
+                    let position = [x as f64 * 8.0, y as f64 * 8.0];
+                    batch.push(
+                        identity(),
+                        position,
+                        [8.0, 8.0],
+                        &sprite(),
+                        Rotation::None,
+                        color,
+                    );
+                }
+            }
+
+            let (sprites, draw_calls) = stats(&batch);
+            println!(
+                "{0}x{0} board: {1} sprites (up to {1} draw calls with a texture per sprite), \
+                 {2} batched draw calls, built in {3:?}",
+                side,
+                sprites,
+                draw_calls,
+                start.elapsed()
+            );
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::point::Point;
 use crate::size::Size;
-use graphics::{Context, Image, Transformed, Viewport};
+use crate::sprite_batch::{Rotation, Sprite, SpriteBatch};
+use graphics::types::Color;
+use graphics::{Context, Transformed, Viewport};
 use opengl_graphics::{GlGraphics, OpenGL, Texture};
 use std::ops::DerefMut;
 
@@ -13,7 +15,7 @@ pub trait GenericContext {
 
     fn settings(&self) -> &SpriteRendererSettings;
 
-    fn graphics(&mut self) -> &mut GlGraphics;
+    fn batch(&mut self) -> &mut SpriteBatch;
 
     fn offset(&self) -> Point {
         (0, 0).into()
@@ -29,36 +31,71 @@ pub trait GenericContext {
         self.size().height
     }
 
-    fn view_mut<P, S>(
-        &mut self,
-        offset: P,
-        size: S,
-    ) -> SubSpriteRenderingContext<&mut Self>
+    fn view_mut<P, S>(&mut self, offset: P, size: S) -> SubSpriteRenderingContext<&mut Self>
     where
         P: Into<Point>,
         S: Into<Size>,
-        Self: Sized
+        Self: Sized,
     {
         SubSpriteRenderingContext::new(self, offset, size)
     }
 
-    fn draw_sprite<P: Into<Point>>(&mut self, coords: P, sprite: &Texture) {
-        let image = Image::new();
+    fn draw_sprite<P: Into<Point>>(&mut self, coords: P, sprite: &Sprite) {
+        self.draw_sprite_rotated(coords, sprite, Rotation::None, [1.0; 4]);
+    }
 
+    /// Draws the sprite multiplied by the color, the alpha of the color sets the opacity.
+    fn draw_sprite_rotated<P: Into<Point>>(
+        &mut self,
+        coords: P,
+        sprite: &Sprite,
+        rotation: Rotation,
+        color: Color,
+    ) {
         let coords: Point = coords.into();
 
-        // get the settings from here somehow
-        let sprite_size = self.settings().sprite_size;
+        let sprite_size = self.settings().sprite_size as f64;
         let offset = self.offset();
+        let transform = self.context().transform;
+
+        self.batch().push(
+            transform,
+            [
+                sprite_size * (coords.x + offset.x) as f64,
+                sprite_size * (coords.y + offset.y) as f64,
+            ],
+            [sprite_size, sprite_size],
+            sprite,
+            rotation,
+            color,
+        );
+    }
 
-        let context = self.context();
-
-        let transform = context.transform.trans_pos([
-            (sprite_size * (coords.x + offset.x) as u32) as f64,
-            (sprite_size * (coords.y + offset.y) as u32) as f64,
-        ]);
-
-        image.draw(sprite, &context.draw_state, transform, self.graphics());
+    /// Draws the sprite stretched over a rectangle which doesn't have to be aligned to cells.
+    ///
+    /// Both `position` and `size` are measured in cells.
+    fn draw_sprite_at(
+        &mut self,
+        position: [f64; 2],
+        size: [f64; 2],
+        sprite: &Sprite,
+        color: Color,
+    ) {
+        let sprite_size = self.settings().sprite_size as f64;
+        let offset = self.offset();
+        let transform = self.context().transform;
+
+        self.batch().push(
+            transform,
+            [
+                sprite_size * (position[0] + offset.x as f64),
+                sprite_size * (position[1] + offset.y as f64),
+            ],
+            [sprite_size * size[0], sprite_size * size[1]],
+            sprite,
+            Rotation::None,
+            color,
+        );
     }
 }
 
@@ -67,9 +104,39 @@ pub struct SpriteRendererSettings {
     sprite_size: u32,
 }
 
+/// Placement of the game field inside of the window, in pixels.
+#[derive(PartialEq, Debug)]
+pub struct Letterbox {
+    pub scale: u32,
+    pub offset: [u32; 2],
+}
+
+impl Letterbox {
+    /// Picks the largest integer scale at which the content fits the viewport and centers it.
+    ///
+    /// The scale never goes below 1, the content is cropped when the viewport is too small.
+    pub fn fit(viewport_size: [u32; 2], content_size: [u32; 2]) -> Self {
+        let [viewport_width, viewport_height] = viewport_size;
+        let [content_width, content_height] = content_size;
+
+        let scale = (viewport_width / content_width.max(1))
+            .min(viewport_height / content_height.max(1))
+            .max(1);
+
+        Letterbox {
+            scale,
+            offset: [
+                viewport_width.saturating_sub(content_width * scale) / 2,
+                viewport_height.saturating_sub(content_height * scale) / 2,
+            ],
+        }
+    }
+}
+
 pub struct SpriteRenderer {
     gl: GlGraphics,
     settings: SpriteRendererSettings,
+    batch: SpriteBatch,
 }
 
 pub struct SpriteRenderingContext<'a> {
@@ -95,19 +162,47 @@ impl SpriteRendererSettings {
 impl SpriteRenderer {
     pub fn new(opengl: OpenGL, settings: SpriteRendererSettings) -> Self {
         let gl = GlGraphics::new(opengl);
-        SpriteRenderer { gl, settings }
+        SpriteRenderer {
+            gl,
+            settings,
+            batch: SpriteBatch::new(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: SpriteRendererSettings) {
+        self.settings = settings;
     }
 
-    pub fn draw<F, U>(&mut self, viewport: Viewport, f: F)
+    /// Draws a frame, all sprites have to come from the atlas texture.
+    pub fn draw<F, U>(&mut self, viewport: Viewport, atlas: &Texture, f: F)
     where
         F: FnOnce(&mut SpriteRenderingContext) -> U,
     {
         let context = self.gl.draw_begin(viewport);
 
+        // the letterbox is fitted in framebuffer pixels so that sprites stay crisp on HiDPI
+        let pixel_ratio = viewport.draw_size[0] as f64 / viewport.window_size[0].max(1.0);
+        let sprite_size = self.settings.sprite_size;
+        let letterbox = Letterbox::fit(
+            viewport.draw_size,
+            [
+                self.settings.size.width * sprite_size,
+                self.settings.size.height * sprite_size,
+            ],
+        );
+
+        let context = context
+            .trans(
+                letterbox.offset[0] as f64 / pixel_ratio,
+                letterbox.offset[1] as f64 / pixel_ratio,
+            )
+            .zoom(letterbox.scale as f64 / pixel_ratio);
+
         let mut sprite_rendering_context = SpriteRenderingContext::new(self, context);
 
         f(&mut sprite_rendering_context);
 
+        self.batch.submit(&mut self.gl, &context.draw_state, atlas);
         self.gl.draw_end();
     }
 }
@@ -120,7 +215,17 @@ impl<'a> SpriteRenderingContext<'a> {
         }
     }
 
+    /// Moves everything drawn afterwards by a distance measured in cells.
+    pub fn translate(&mut self, cells: [f64; 2]) {
+        let sprite_size = self.sprite_renderer.settings.sprite_size as f64;
+        self.context = self
+            .context
+            .trans(cells[0] * sprite_size, cells[1] * sprite_size);
+    }
+
+    /// Clears the screen, dropping everything drawn so far in the frame.
     pub fn clear(&mut self, color: graphics::types::Color) {
+        self.sprite_renderer.batch.clear();
         graphics::clear(color, &mut self.sprite_renderer.gl);
     }
 }
@@ -140,8 +245,8 @@ impl<'a> GenericContext for SpriteRenderingContext<'a> {
         &self.sprite_renderer.settings
     }
 
-    fn graphics(&mut self) -> &mut GlGraphics {
-        &mut self.sprite_renderer.gl
+    fn batch(&mut self) -> &mut SpriteBatch {
+        &mut self.sprite_renderer.batch
     }
 
     fn size(&self) -> Size {
@@ -167,7 +272,7 @@ where
         SubSpriteRenderingContext {
             inner_context,
             offset: inner_offset.offset(offset),
-            size: size,
+            size,
         }
     }
 }
@@ -191,8 +296,8 @@ where
         self.inner_context.settings()
     }
 
-    fn graphics(&mut self) -> &mut GlGraphics {
-        self.inner_mut().graphics()
+    fn batch(&mut self) -> &mut SpriteBatch {
+        self.inner_mut().batch()
     }
 
     fn size(&self) -> Size {
@@ -203,3 +308,51 @@ where
         self.offset
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_letterbox_exact_fit() {
+        assert_eq!(
+            Letterbox::fit([576, 384], [192, 128]),
+            Letterbox {
+                scale: 3,
+                offset: [0, 0],
+            }
+        );
+    }
+
+    #[test]
+    fn test_letterbox_centers_content() {
+        // limited by height, bars on the sides
+        assert_eq!(
+            Letterbox::fit([1920, 1080], [192, 128]),
+            Letterbox {
+                scale: 8,
+                offset: [192, 28],
+            }
+        );
+
+        // limited by width, bars on top and bottom
+        assert_eq!(
+            Letterbox::fit([400, 1000], [192, 128]),
+            Letterbox {
+                scale: 2,
+                offset: [8, 372],
+            }
+        );
+    }
+
+    #[test]
+    fn test_letterbox_too_small() {
+        assert_eq!(
+            Letterbox::fit([100, 50], [192, 128]),
+            Letterbox {
+                scale: 1,
+                offset: [0, 0],
+            }
+        );
+    }
+}
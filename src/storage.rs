@@ -0,0 +1,98 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const APP_DIRECTORY: &str = "snake_game";
+
+/// Directory where the game keeps the files it writes, like saved games.
+pub struct Storage {
+    directory: PathBuf,
+}
+
+impl Storage {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Storage {
+            directory: directory.into(),
+        }
+    }
+
+    /// Data directory of the user: `%APPDATA%\snake_game` on Windows, otherwise
+    /// `$XDG_DATA_HOME/snake_game` or `~/.local/share/snake_game`.
+    pub fn user_data() -> Option<Self> {
+        let non_empty = |name| env::var_os(name).filter(|value| !value.is_empty());
+
+        let base = if cfg!(windows) {
+            non_empty("APPDATA").map(PathBuf::from)
+        } else {
+            non_empty("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        };
+
+        base.map(|base| Storage::new(base.join(APP_DIRECTORY)))
+    }
+
+    /// Storage in a directory inside of this one.
+    pub fn subdirectory(&self, name: &str) -> Self {
+        Storage::new(self.directory.join(name))
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.directory.join(name).is_file()
+    }
+
+    /// Reads the file, or returns `None` when there is no such file.
+    pub fn read(&self, name: &str) -> io::Result<Option<String>> {
+        match fs::read_to_string(self.directory.join(name)) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Replaces the file, a crash while writing leaves the previous version in place.
+    pub fn write(&self, name: &str, contents: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+
+        let path = self.directory.join(name);
+        let temporary_path = self.directory.join(format!("{}.tmp", name));
+        fs::write(&temporary_path, contents)?;
+        fs::rename(&temporary_path, &path)
+    }
+
+    pub fn remove(&self, name: &str) -> io::Result<()> {
+        match fs::remove_file(self.directory.join(name)) {
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_read_remove() {
+        let directory = env::temp_dir().join(format!("snake_game_storage_{}", std::process::id()));
+        let storage = Storage::new(directory.join("nested"));
+
+        assert!(!storage.exists("file.txt"));
+        assert_eq!(storage.read("file.txt").unwrap(), None);
+
+        storage.write("file.txt", "first").unwrap();
+        storage.write("file.txt", "second").unwrap();
+        assert!(storage.exists("file.txt"));
+        assert_eq!(
+            storage.read("file.txt").unwrap(),
+            Some("second".to_string())
+        );
+
+        storage.remove("file.txt").unwrap();
+        storage.remove("file.txt").unwrap();
+        assert!(!storage.exists("file.txt"));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}